@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    example::ExampleTile,
+    grid::Grid,
+    tile_data::{Sockets, TileData, TileId},
+    topology::Topology,
+    Direction,
+};
+
+// Which tiles can be placed next to each other, this is what the solver checks when propagating
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjacencyRules {
+    // Indexed by Direction, TileId and neighbour TileId
+    allowed: Vec<Vec<Vec<bool>>>,
+}
+
+impl AdjacencyRules {
+    // Rules where no tiles can be placed next to each other
+    pub fn new(tile_count: usize) -> Self {
+        Self {
+            allowed: vec![vec![vec![false; tile_count]; tile_count]; Direction::COUNT],
+        }
+    }
+
+    // Rules where tiles fit next to each other when their edges connect
+    pub fn from_edges(tile_datas: &[TileData], sockets: &Sockets) -> Self {
+        let mut rules = Self::new(tile_datas.len());
+        for direction in Direction::iterator() {
+            for (tile, tile_data) in tile_datas.iter().enumerate() {
+                for (neighbour, neighbour_data) in tile_datas.iter().enumerate() {
+                    rules.allowed[direction as usize][tile][neighbour] = neighbour_data.check_edge(
+                        direction.get_opposite(),
+                        tile_data.get_edge(direction),
+                        tile_data.get_suffix(direction),
+                        sockets,
+                    );
+                }
+            }
+        }
+        rules
+    }
+
+    // Lets neighbour be placed in the direction of tile, and tile in the opposite direction of neighbour
+    pub fn allow(&mut self, tile: TileId, direction: Direction, neighbour: TileId) {
+        self.allowed[direction as usize][tile][neighbour] = true;
+        self.allowed[direction.get_opposite() as usize][neighbour][tile] = true;
+    }
+
+    // Stops neighbour from being placed in the direction of tile, and the other way around
+    pub fn deny(&mut self, tile: TileId, direction: Direction, neighbour: TileId) {
+        self.allowed[direction as usize][tile][neighbour] = false;
+        self.allowed[direction.get_opposite() as usize][neighbour][tile] = false;
+    }
+
+    // Returns: whether neighbour can be placed in the direction of tile
+    pub fn is_allowed(&self, tile: TileId, direction: Direction, neighbour: TileId) -> bool {
+        self.allowed[direction as usize][tile][neighbour]
+    }
+
+    // Returns: every tile that can be placed in the direction of tile
+    pub fn allowed_neighbours(&self, tile: TileId, direction: Direction) -> Vec<TileId> {
+        let allowed = &self.allowed[direction as usize][tile];
+        (0..allowed.len())
+            .filter(|neighbour| allowed[*neighbour])
+            .collect()
+    }
+}
+
+// Learns a tile for every different tile in an example map, weighted by how often it occurs.
+// Tiles can only be placed next to each other in the directions they are next to each other
+// in the example.
+pub fn learn_tiles(example: &Grid<ExampleTile>) -> (Vec<TileData>, AdjacencyRules) {
+    // Sorted so the same example always gives the same tiles
+    let mut counts = BTreeMap::<&ExampleTile, u32>::new();
+    for tile in example.cells() {
+        *counts.entry(tile).or_insert(0) += 1;
+    }
+    let tile_ids = counts
+        .keys()
+        .enumerate()
+        .map(|(tile_id, tile)| (*tile, tile_id))
+        .collect::<BTreeMap<&ExampleTile, TileId>>();
+
+    let mut rules = AdjacencyRules::new(counts.len());
+    for (position, tile) in example.iter() {
+        for direction in Topology::Square.iterator() {
+            if let Some(neighbour_position) =
+                Topology::Square.neighbour(position, direction, example.size())
+            {
+                let neighbour = example.get(neighbour_position);
+                rules.allow(tile_ids[tile], direction, tile_ids[neighbour]);
+            }
+        }
+    }
+    // The example is a single layer, so it doesn't limit which tiles are stacked
+    for tile in 0..counts.len() {
+        for neighbour in 0..counts.len() {
+            rules.allow(tile, Direction::Top, neighbour);
+        }
+    }
+
+    let tile_datas = counts
+        .into_iter()
+        .map(|(tile, count)| TileData {
+            model: tile.model.clone(),
+            rotation: tile.rotation,
+            weight: count,
+            ..Default::default()
+        })
+        .collect();
+    (tile_datas, rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{learn_tiles, AdjacencyRules};
+    use crate::{
+        config::WfcConfig,
+        example::parse_example,
+        tile_data::{Sockets, TileData},
+        topology::Topology,
+        wave_function_collapse::WFC,
+        Direction,
+    };
+
+    #[test]
+    fn rules_from_edges() {
+        let tile = |up_edge: &str| TileData {
+            up_edge: up_edge.to_owned(),
+            down_edge: "grass".to_owned(),
+            ..Default::default()
+        };
+        let rules = AdjacencyRules::from_edges(&[tile("grass"), tile("pond")], &Sockets::default());
+
+        assert!(rules.is_allowed(0, Direction::Up, 1));
+        assert!(rules.is_allowed(1, Direction::Down, 0));
+        assert!(!rules.is_allowed(1, Direction::Up, 0));
+    }
+
+    #[test]
+    fn generated_map_only_has_learned_neighbours() {
+        // Water is never directly next to the forest
+        let example = parse_example(
+            "forest, sand, water, water\nforest, sand, sand, water\nforest, forest, sand, sand",
+            "test",
+        )
+        .unwrap();
+        let (tile_datas, rules) = learn_tiles(&example);
+        assert_eq!(tile_datas.len(), 3);
+
+        let config = WfcConfig::builder()
+            .map_size(12)
+            .max_backtracks(100)
+            .seed(7)
+            .build()
+            .unwrap();
+        let grid =
+            WFC::with_rules(&tile_datas, Topology::Square, rules.clone()).place_tiles(&config);
+
+        for (position, tile) in grid.iter() {
+            let tile = tile.expect("Every position has a tile");
+            for direction in Topology::Square.iterator() {
+                if let Some(neighbour_position) =
+                    Topology::Square.neighbour(position, direction, grid.size())
+                {
+                    let neighbour = grid
+                        .get(neighbour_position)
+                        .expect("Every position has a tile");
+                    assert!(rules.is_allowed(tile, direction, neighbour));
+                }
+            }
+        }
+    }
+}
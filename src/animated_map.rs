@@ -0,0 +1,113 @@
+use nalgebra::Vector3;
+
+use crate::{
+    config::WfcConfig,
+    scene::Scene,
+    topology::Topology,
+    wave_function_collapse::{self, LoadedTileset, Stepper},
+    Color, Triangle,
+};
+
+// Size and color of the boxes shown where no tile has been placed yet
+const PLACEHOLDER_SIZE: f32 = 0.5;
+const PLACEHOLDER_COLOR: Color = Color::new(0.8, 0.8, 0.9);
+
+// A single map that is generated a few collapses every frame, so the generation can be watched.
+// Tile positions that haven't been collapsed yet are shown as translucent boxes, which are more
+// solid the more entropy the tile position has left.
+pub struct AnimatedMap {
+    stepper: Stepper<'static>,
+    models: Vec<Vec<Triangle>>,
+    topology: Topology,
+    steps_per_frame: usize,
+}
+
+impl AnimatedMap {
+    // Reads the tileset, models and pins of the config
+    pub fn load(config: &WfcConfig) -> anyhow::Result<Self> {
+        Self::new(LoadedTileset::load(config)?, config)
+    }
+
+    pub fn new(loaded: LoadedTileset, config: &WfcConfig) -> anyhow::Result<Self> {
+        let LoadedTileset { tileset, models } = loaded;
+        let topology = tileset.topology;
+        let stepper = wave_function_collapse::create_solver(config, tileset)?
+            .into_stepper(config, config.placement_strategy.cell_selector());
+        Ok(Self {
+            stepper,
+            models,
+            topology,
+            steps_per_frame: config.animation_steps,
+        })
+    }
+
+    // Collapses the next animation_steps tile positions
+    // Returns: whether the map has changed
+    pub fn update(&mut self) -> bool {
+        self.stepper.by_ref().take(self.steps_per_frame).count() > 0
+    }
+
+    // Places the models of the tiles placed so far and the placeholders in a new scene
+    pub fn scene(&self) -> Scene {
+        let mut scene = Scene::new();
+        scene.instantiate_grid(
+            &self.stepper.grid(),
+            &self.models,
+            self.topology,
+            Vector3::zeros(),
+        );
+
+        let wave = self.stepper.wave();
+        let max_entropy = self.stepper.max_entropy();
+        for tile_index in wave.uncollapsed_tiles() {
+            let density = match max_entropy > 0.0 {
+                true => self.stepper.entropy(*tile_index) / max_entropy,
+                false => 0.0,
+            };
+            scene.instantiate_box(
+                self.topology
+                    .world_position(wave.tiles()[*tile_index].tile_position),
+                PLACEHOLDER_SIZE,
+                PLACEHOLDER_COLOR,
+                0.05 + 0.5 * density,
+            );
+        }
+        scene
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnimatedMap;
+    use crate::{
+        config::WfcConfig, test_tiles::tile, tileset::Tileset, topology::Topology,
+        wave_function_collapse::LoadedTileset,
+    };
+
+    #[test]
+    fn placeholders_are_replaced_by_tiles() {
+        let tileset = Tileset::new(
+            vec![
+                tile(1, ["grass", "grass", "grass", "grass"]),
+                tile(1, ["road", "grass", "road", "grass"]),
+                tile(1, ["grass", "road", "grass", "road"]),
+            ],
+            Topology::Square,
+        );
+        let config = WfcConfig::builder()
+            .map_size(4)
+            .animation_steps(5)
+            .seed(2)
+            .build()
+            .unwrap();
+        let mut map = AnimatedMap::new(LoadedTileset::without_models(tileset), &config).unwrap();
+
+        // Every box has 12 triangles
+        assert_eq!(map.scene().translucent_triangles.len(), 16 * 12);
+        assert!(map.update());
+        assert_eq!(map.scene().translucent_triangles.len(), 11 * 12);
+        while map.update() {}
+        assert!(map.scene().translucent_triangles.is_empty());
+        assert!(map.stepper.grid().cells().iter().all(Option::is_some));
+    }
+}
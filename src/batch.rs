@@ -0,0 +1,163 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+use crate::{
+    config::{usable_seed, WfcConfig},
+    grid::Grid,
+    saved_map::SavedMap,
+    scene::Scene,
+    tile_data::TileId,
+    wave_function_collapse::{self, LoadedTileset, WFC},
+};
+
+// Scores a generated map, called from several threads at the same time
+pub type ScoreFunction = Box<dyn Fn(&Grid<Option<TileId>>) -> f32 + Send + Sync>;
+
+// How the maps of a batch are compared, a higher score is better
+pub enum Scoring {
+    FilledFraction,       // Share of the tile positions that got a tile
+    FewestContradictions, // Minus the number of times tile positions ran out of options
+    Custom(ScoreFunction),
+}
+
+impl Scoring {
+    pub fn score(&self, grid: &Grid<Option<TileId>>, contradictions: u32) -> f32 {
+        match self {
+            Scoring::FilledFraction => {
+                let filled = grid.cells().iter().filter(|tile| tile.is_some()).count();
+                filled as f32 / grid.cells().len() as f32
+            }
+            Scoring::FewestContradictions => -(contradictions as f32),
+            Scoring::Custom(score) => score(grid),
+        }
+    }
+}
+
+// A map generated with one seed of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub seed: u64,
+    pub grid: Grid<Option<TileId>>,
+    pub contradictions: u32,
+    pub score: f32,
+}
+
+// Returns: count seeds in a row, starting with the seed of the config or a random one when it is 0
+pub fn seeds(config: &WfcConfig, count: usize) -> Vec<u64> {
+    let first_seed = config.pick_seed();
+    (0..count as u64)
+        .map(|offset| usable_seed(first_seed.wrapping_add(offset)))
+        .collect()
+}
+
+// Generates a map for every seed at the same time, the rest of the config is the same for every map
+// Returns: the maps sorted from the best to the worst score, maps with the same score stay in the
+// order of their seeds
+pub fn generate_batch(
+    wfc: &WFC,
+    config: &WfcConfig,
+    seeds: &[u64],
+    scoring: &Scoring,
+) -> Vec<BatchResult> {
+    let mut results = seeds
+        .par_iter()
+        .map(|seed| {
+            let config = WfcConfig {
+                seed: *seed,
+                ..config.clone()
+            };
+            let mut stepper = wfc.stepper(&config, config.placement_strategy.cell_selector());
+            stepper.by_ref().for_each(drop);
+
+            let grid = stepper.grid();
+            let contradictions = stepper.contradictions();
+            BatchResult {
+                seed: *seed,
+                score: scoring.score(&grid, contradictions),
+                grid,
+                contradictions,
+            }
+        })
+        .collect::<Vec<BatchResult>>();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+// Returns: the map with the best score of the batch, see generate_batch
+pub fn generate_best(
+    wfc: &WFC,
+    config: &WfcConfig,
+    seeds: &[u64],
+    scoring: &Scoring,
+) -> Option<BatchResult> {
+    generate_batch(wfc, config, seeds, scoring)
+        .into_iter()
+        .next()
+}
+
+// Generates seed_count maps and places the models of the most filled one in a new scene
+// Returns: the scene, the map it shows and how it scored
+pub fn generate_best_scene(
+    config: &WfcConfig,
+    seed_count: usize,
+) -> anyhow::Result<(Scene, SavedMap, BatchResult)> {
+    let LoadedTileset { tileset, models } = LoadedTileset::load(config)?;
+    let wfc = wave_function_collapse::create_solver(config, tileset.clone())?;
+
+    let best = generate_best(
+        &wfc,
+        config,
+        &seeds(config, seed_count.max(1)),
+        &Scoring::FilledFraction,
+    )
+    .expect("There is at least one seed");
+    let mut scene = Scene::new();
+    scene.instantiate_grid(&best.grid, &models, tileset.topology, Vector3::zeros());
+    let map = SavedMap::new(config, best.seed, &best.grid, &tileset);
+    Ok((scene, map, best))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_batch, seeds, Scoring};
+    use crate::{
+        config::WfcConfig, test_tiles::three_colors, topology::Topology,
+        wave_function_collapse::WFC,
+    };
+
+    #[test]
+    fn best_map_comes_first() {
+        let (tile_datas, rules) = three_colors();
+        let wfc = WFC::with_rules(&tile_datas, Topology::Square, rules);
+        let config = WfcConfig::builder().map_size(8).seed(5).build().unwrap();
+        let seeds = seeds(&config, 16);
+        assert_eq!(seeds[..3], [5, 6, 7]);
+
+        let results = generate_batch(&wfc, &config, &seeds, &Scoring::FilledFraction);
+        assert_eq!(results.len(), 16);
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert!(results[0].score > results[15].score);
+        // Every map is the same as when it is generated on its own
+        for result in &results {
+            let config = WfcConfig {
+                seed: result.seed,
+                ..config.clone()
+            };
+            assert_eq!(wfc.place_tiles(&config), result.grid);
+        }
+
+        let results = generate_batch(&wfc, &config, &seeds, &Scoring::FewestContradictions);
+        assert_eq!(results[0].contradictions, 0);
+        assert!(results[15].contradictions > 0);
+
+        let first_color = Scoring::Custom(Box::new(|grid| {
+            grid.cells().iter().filter(|tile| **tile == Some(0)).count() as f32
+        }));
+        let results = generate_batch(&wfc, &config, &seeds, &first_color);
+        assert!(results
+            .iter()
+            .all(|result| result.score <= results[0].score));
+    }
+}
@@ -0,0 +1,138 @@
+use nalgebra::Vector3;
+
+use kth_dd2323_project::{
+    batch, config::WfcConfig, gltf_export, map_output, obj_export, saved_map::SavedMap,
+    scene::Scene, wave_function_collapse,
+};
+
+const USAGE: &str =
+    "Usage: wfc-gen [--config PATH] [--seeds N] [--format text|json|map|glb|obj] [--output PATH]
+
+Generates a map without opening a window and writes the tile of every tile position.
+  --config PATH      Config file to read, ./config.txt by default
+  --seeds N          Generates N maps at the same time and writes the most filled one
+  --format FORMAT    text writes the map like an example map, json also writes the tileset,
+                     seed and size, map saves the map with its config so the viewer can
+                     show it again with --load, glb writes the models of the tiles as a glTF scene and
+                     obj as an OBJ file with an MTL file next to it. text by default
+  --output PATH      File to write the map to, the terminal by default";
+
+// How the generated map is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Map,
+    Glb,
+    Obj,
+}
+
+struct Arguments {
+    config_path: String,
+    seed_count: usize,
+    format: Format,
+    output_path: Option<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let Some(arguments) = parse_arguments(std::env::args().skip(1).collect())? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    anyhow::ensure!(
+        !matches!(arguments.format, Format::Glb | Format::Obj) || arguments.output_path.is_some(),
+        "glb and obj need an --output file"
+    );
+
+    let config = WfcConfig::load(&arguments.config_path)?;
+    let tileset = wave_function_collapse::load_tileset(&config)?;
+    let wfc = wave_function_collapse::create_solver(&config, tileset.clone())?;
+    let best = batch::generate_best(
+        &wfc,
+        &config,
+        &batch::seeds(&config, arguments.seed_count),
+        &batch::Scoring::FilledFraction,
+    )
+    .expect("There is at least one seed");
+    eprintln!(
+        "Seed {} filled {:.1}% of the map",
+        best.seed,
+        best.score * 100.0
+    );
+
+    let scene = || -> anyhow::Result<Scene> {
+        let mut scene = Scene::new();
+        scene.instantiate_grid(
+            &best.grid,
+            &tileset.load_models()?,
+            tileset.topology,
+            Vector3::zeros(),
+        );
+        Ok(scene)
+    };
+    let output = match arguments.format {
+        Format::Text => map_output::to_text(&best.grid, &tileset).into_bytes(),
+        Format::Json => map_output::to_json(&best.grid, &tileset, best.seed).into_bytes(),
+        Format::Map => SavedMap::new(&config, best.seed, &best.grid, &tileset)
+            .to_string()
+            .into_bytes(),
+        Format::Glb => gltf_export::to_glb(&scene()?),
+        // Writes two files
+        Format::Obj => {
+            let path = arguments.output_path.expect("Checked above");
+            return obj_export::save_obj(&scene()?, path);
+        }
+    };
+    match arguments.output_path {
+        Some(path) => std::fs::write(&path, output)
+            .map_err(|error| anyhow::format_err!("Could not write {}: {}", path, error))?,
+        None => print!("{}", String::from_utf8_lossy(&output)),
+    }
+    Ok(())
+}
+
+// Returns: the arguments, None when the usage should be shown
+fn parse_arguments(arguments: Vec<String>) -> anyhow::Result<Option<Arguments>> {
+    let mut parsed = Arguments {
+        config_path: "./config.txt".to_owned(),
+        seed_count: 1,
+        format: Format::Text,
+        output_path: None,
+    };
+
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        if argument == "--help" || argument == "-h" {
+            return Ok(None);
+        }
+        let value = arguments
+            .next()
+            .ok_or_else(|| anyhow::format_err!("{} needs a value\n\n{}", argument, USAGE))?;
+        match argument.as_str() {
+            "--config" => parsed.config_path = value,
+            "--seeds" => {
+                parsed.seed_count = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| {
+                        anyhow::format_err!("--seeds needs a number of seeds larger than 0")
+                    })?;
+            }
+            "--format" => {
+                parsed.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "map" => Format::Map,
+                    "glb" => Format::Glb,
+                    "obj" => Format::Obj,
+                    _ => anyhow::bail!("The format can only be text, json, map, glb or obj"),
+                }
+            }
+            "--output" => parsed.output_path = Some(value),
+            _ => anyhow::bail!("{} is not an option\n\n{}", argument, USAGE),
+        }
+    }
+    Ok(Some(parsed))
+}
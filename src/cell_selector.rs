@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, seq::IteratorRandom};
+
+use crate::wave_function_collapse::Wave;
+
+// Chooses which tile position is collapsed next. The solver collapses the chosen tile position,
+// propagates the change and asks again until every tile position is collapsed, the max iteration
+// count is reached or the selector returns None. A new selector is used for every map.
+pub trait CellSelector {
+    // Returns: the index of an uncollapsed tile position in the tiles of the wave
+    fn next_tile(&mut self, wave: &mut Wave, random: &mut StdRng) -> Option<usize>;
+
+    // Selectors that use Wave::pop_least_entropy have to return true, keeping track of the
+    // entropy slows down the solver for the others
+    fn uses_entropy(&self) -> bool {
+        false
+    }
+}
+
+// Starts at the start tile and then chooses random tile positions
+#[derive(Debug, Default)]
+pub struct RandomSelector {
+    started: bool,
+}
+
+impl CellSelector for RandomSelector {
+    fn next_tile(&mut self, wave: &mut Wave, random: &mut StdRng) -> Option<usize> {
+        if !self.started {
+            self.started = true;
+            return wave.start_tiles().first().copied();
+        }
+        wave.uncollapsed_tiles().iter().choose(random).copied()
+    }
+}
+
+// Collapses tile positions in the order of a BFS from the start tiles
+#[derive(Debug, Default)]
+pub struct GrowingSelector {
+    tiles_queue: VecDeque<usize>,
+    started: bool,
+    last_tile: Option<usize>,
+}
+
+impl CellSelector for GrowingSelector {
+    fn next_tile(&mut self, wave: &mut Wave, _random: &mut StdRng) -> Option<usize> {
+        if !self.started {
+            self.started = true;
+            self.tiles_queue.extend(wave.start_tiles());
+        }
+        // Add the neighbours of the tile that was collapsed last, a tile could already be in queue
+        if let Some(last_tile) = self.last_tile {
+            for neighbour_index in wave.neighbours(last_tile) {
+                if wave.tiles()[neighbour_index].data.is_none() {
+                    self.tiles_queue.push_back(neighbour_index);
+                }
+            }
+        }
+
+        loop {
+            let Some(tile_index) = self.tiles_queue.pop_front() else {
+                if wave.uncollapsed_tiles().is_empty() {
+                    return None;
+                }
+                // Backtracking has undone tiles that were already visited
+                self.tiles_queue.extend(wave.uncollapsed_tiles());
+                continue;
+            };
+
+            // Tiles without options are still grown through
+            if wave.tiles()[tile_index].data.is_none() {
+                self.last_tile = Some(tile_index);
+                return Some(tile_index);
+            }
+        }
+    }
+}
+
+// Collapses tile positions from left to right, down to up, one layer at a time from the bottom
+#[derive(Debug, Default)]
+pub struct OrderedSelector;
+
+impl CellSelector for OrderedSelector {
+    fn next_tile(&mut self, wave: &mut Wave, _random: &mut StdRng) -> Option<usize> {
+        // The lowest uncollapsed index is the next tile in order, also after backtracking
+        wave.uncollapsed_tiles().first().copied()
+    }
+}
+
+// Starts at the start tile and then collapses the tile position with the least entropy
+#[derive(Debug, Default)]
+pub struct LeastEntropySelector {
+    started: bool,
+}
+
+impl CellSelector for LeastEntropySelector {
+    fn next_tile(&mut self, wave: &mut Wave, _random: &mut StdRng) -> Option<usize> {
+        if !self.started {
+            self.started = true;
+            return wave.start_tiles().first().copied();
+        }
+        wave.pop_least_entropy()
+    }
+
+    fn uses_entropy(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::ensure;
+use nalgebra::{Vector2, Vector3};
+
+use crate::{
+    adjacency::AdjacencyRules,
+    config::{usable_seed, WfcConfig},
+    grid::Grid,
+    pins::{Pin, PinArea},
+    scene::Scene,
+    tile_data::TileId,
+    topology::{Boundary, Topology},
+    wave_function_collapse::{self, LoadedTileset, WFC},
+    Direction, Triangle,
+};
+
+// Chunks generated at most in one update, so the window keeps drawing while chunks are loading.
+// A chunk that has to fit chunks that aren't generated yet generates those as well
+const CHUNKS_PER_UPDATE: usize = 2;
+// Chunks further than chunk_radius + this from the camera are dropped, they are the same when
+// they are generated again
+const KEPT_CHUNKS_MARGIN: i32 = 2;
+
+// A world without an end that is split into chunks of map_width * map_height tiles, which are
+// generated when the camera gets close to them.
+//
+// Each chunk has to fit the chunks next to it, so chunks are generated in an order that only
+// depends on their coordinate. Chunks with an even x and y are generated on their own, chunks
+// with one odd coordinate fit the even chunks on two sides and chunks with two odd coordinates
+// fit the chunks on all four sides. This way a chunk always looks the same for a seed, no matter
+// where the camera has been. Weight maps and count limits apply to every chunk on its own.
+pub struct ChunkedWorld {
+    solver: WFC<'static>, // Without pins, every chunk pins the seams to its neighbours
+    rules: AdjacencyRules, // The rules of the solver, to find the tiles that fit at the seams
+    topology: Topology,
+    models: Vec<Vec<Triangle>>,
+    config: WfcConfig,
+    seed: u64,
+    chunks: HashMap<Vector2<i32>, Grid<Option<TileId>>>,
+    visible_chunks: BTreeSet<(i32, i32)>,
+}
+
+impl ChunkedWorld {
+    // Reads the tileset and models of the config
+    pub fn load(config: &WfcConfig) -> anyhow::Result<Self> {
+        Self::new(LoadedTileset::load(config)?, config)
+    }
+
+    pub fn new(loaded: LoadedTileset, config: &WfcConfig) -> anyhow::Result<Self> {
+        let LoadedTileset { tileset, models } = loaded;
+        ensure!(
+            tileset.topology == Topology::Square,
+            "Chunked worlds only work with the square topology"
+        );
+
+        // The pins of the config are left out since an endless world has no fixed positions
+        let topology = tileset.topology;
+        let rules = tileset.rules.clone();
+        let solver = wave_function_collapse::create_solver(
+            &WfcConfig {
+                pins_path: String::new(),
+                ..config.clone()
+            },
+            tileset,
+        )?;
+        // The seed is picked once so every chunk uses the same one
+        let seed = config.pick_seed();
+        Ok(Self {
+            solver,
+            rules,
+            topology,
+            models,
+            config: config.clone(),
+            seed,
+            chunks: HashMap::new(),
+            visible_chunks: BTreeSet::new(),
+        })
+    }
+
+    // Generates the next chunks within chunk_radius chunks of the position, the closest first.
+    // Only a few chunks are generated every update, call it again until it returns false
+    // Returns: whether other chunks are visible than before
+    pub fn update(&mut self, position: Vector3<f32>) -> bool {
+        let center = Vector2::new(
+            (position.x / self.config.map_width as f32).floor() as i32,
+            (position.y / self.config.map_height as f32).floor() as i32,
+        );
+        let distance = |coordinate: &Vector2<i32>| {
+            let offset = coordinate - center;
+            offset.x.abs().max(offset.y.abs())
+        };
+        let radius = self.config.chunk_radius as i32;
+        let mut coordinates = (center.y - radius..=center.y + radius)
+            .flat_map(|y| (center.x - radius..=center.x + radius).map(move |x| Vector2::new(x, y)))
+            .collect::<Vec<Vector2<i32>>>();
+        coordinates.sort_by_key(distance);
+
+        let mut generated = 0;
+        let mut visible_chunks = BTreeSet::new();
+        for coordinate in coordinates {
+            if generated < CHUNKS_PER_UPDATE {
+                generated += self.generate_chunk(coordinate);
+            }
+            if self.chunks.contains_key(&coordinate) {
+                visible_chunks.insert((coordinate.x, coordinate.y));
+            }
+        }
+        self.chunks
+            .retain(|coordinate, _| distance(coordinate) <= radius + KEPT_CHUNKS_MARGIN);
+
+        let changed = visible_chunks != self.visible_chunks;
+        self.visible_chunks = visible_chunks;
+        changed
+    }
+
+    // Places the models of the visible chunks in a new scene
+    pub fn scene(&self) -> Scene {
+        let mut scene = Scene::new();
+        for (x, y) in &self.visible_chunks {
+            let offset = Vector3::new(
+                (x * self.config.map_width as i32) as f32,
+                (y * self.config.map_height as i32) as f32,
+                0.0,
+            );
+            scene.instantiate_grid(
+                &self.chunks[&Vector2::new(*x, *y)],
+                &self.models,
+                self.topology,
+                offset,
+            );
+        }
+        scene
+    }
+
+    // Returns: the tiles of a chunk, generating it and the chunks it has to fit if needed
+    pub fn chunk(&mut self, coordinate: Vector2<i32>) -> &Grid<Option<TileId>> {
+        self.generate_chunk(coordinate);
+        &self.chunks[&coordinate]
+    }
+
+    // Returns: how many chunks were generated
+    fn generate_chunk(&mut self, coordinate: Vector2<i32>) -> usize {
+        if self.chunks.contains_key(&coordinate) {
+            return 0;
+        }
+
+        // Odd coordinates fit the chunks next to them in that axis
+        let mut sides = vec![];
+        if coordinate.x.rem_euclid(2) == 1 {
+            sides.extend([Vector2::new(-1, 0), Vector2::new(1, 0)]);
+        }
+        if coordinate.y.rem_euclid(2) == 1 {
+            sides.extend([Vector2::new(0, -1), Vector2::new(0, 1)]);
+        }
+        let generated = sides
+            .iter()
+            .map(|side| self.generate_chunk(coordinate + side))
+            .sum::<usize>();
+
+        // The tile positions at a seam are pinned to the tiles that fit next to the tile on the
+        // other side of the seam
+        let chunk_size = Vector3::new(
+            self.config.map_width,
+            self.config.map_height,
+            self.config.map_depth,
+        );
+        let (width, height) = (chunk_size.x, chunk_size.y);
+        let mut pins = vec![];
+        for side in &sides {
+            let neighbour = &self.chunks[&(coordinate + side)];
+            // Direction of the neighbour, positions at the seam and positions of the tiles
+            // next to them in the neighbour
+            let (direction, seam) = match (side.x, side.y) {
+                (-1, _) => (
+                    Direction::Left,
+                    (0..height)
+                        .map(|y| ((0, y), (width - 1, y)))
+                        .collect::<Vec<_>>(),
+                ),
+                (1, _) => (
+                    Direction::Right,
+                    (0..height).map(|y| ((width - 1, y), (0, y))).collect(),
+                ),
+                (_, -1) => (
+                    Direction::Down,
+                    (0..width).map(|x| ((x, 0), (x, height - 1))).collect(),
+                ),
+                _ => (
+                    Direction::Up,
+                    (0..width).map(|x| ((x, height - 1), (x, 0))).collect(),
+                ),
+            };
+            for z in 0..chunk_size.z {
+                for ((x, y), (neighbour_x, neighbour_y)) in &seam {
+                    // Tile positions the neighbour couldn't fill don't limit this chunk
+                    if let Some(tile) = neighbour.get(Vector3::new(*neighbour_x, *neighbour_y, z)) {
+                        pins.push(Pin {
+                            area: PinArea::Tile(Vector3::new(*x, *y, z)),
+                            tiles: self
+                                .rules
+                                .allowed_neighbours(*tile, direction.get_opposite()),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Chunks continue into the chunks next to them, so the map has no boundary
+        let config = WfcConfig {
+            boundary: Boundary::Open,
+            seed: chunk_seed(self.seed, coordinate),
+            ..self.config.clone()
+        };
+        let grid = self.solver.clone().with_pins(pins).place_tiles(&config);
+        self.chunks.insert(coordinate, grid);
+        generated + 1
+    }
+}
+
+// Returns: a seed for the chunk that only depends on the world seed and the chunk coordinate
+fn chunk_seed(seed: u64, coordinate: Vector2<i32>) -> u64 {
+    let mut hash = seed
+        ^ (coordinate.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (coordinate.y as u64).rotate_left(32);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    usable_seed(hash ^ (hash >> 31))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Vector2, Vector3};
+
+    use super::ChunkedWorld;
+    use crate::{
+        config::WfcConfig,
+        test_tiles::pond_tiles,
+        tile_count::{Amount, CountLimit},
+        tileset::Tileset,
+        topology::Topology,
+        wave_function_collapse::LoadedTileset,
+        Direction,
+    };
+
+    // Ponds that continue over the edges of the chunks
+    fn pond_tileset() -> Tileset {
+        Tileset::new(pond_tiles(), Topology::Square)
+    }
+
+    fn test_world() -> ChunkedWorld {
+        let config = WfcConfig::builder()
+            .map_size(6)
+            .chunk_radius(1)
+            .max_backtracks(100)
+            .seed(9)
+            .build()
+            .unwrap();
+        ChunkedWorld::new(LoadedTileset::without_models(pond_tileset()), &config).unwrap()
+    }
+
+    #[test]
+    fn chunks_only_depend_on_coordinate() {
+        let mut world = test_world();
+        let chunk = world.chunk(Vector2::new(1, 1)).clone();
+
+        let mut other_world = test_world();
+        other_world.update(Vector3::new(20.0, -3.0, 0.0));
+        other_world.chunk(Vector2::new(2, 1));
+        assert_eq!(*other_world.chunk(Vector2::new(1, 1)), chunk);
+        assert_ne!(*other_world.chunk(Vector2::new(3, 1)), chunk);
+    }
+
+    #[test]
+    fn chunks_fit_at_seams() {
+        let mut world = test_world();
+        world.update(Vector3::new(0.0, 0.0, 0.0));
+        let tileset = pond_tileset();
+
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let chunk = world.chunk(Vector2::new(x, y)).clone();
+                let right = world.chunk(Vector2::new(x + 1, y)).clone();
+                let up = world.chunk(Vector2::new(x, y + 1)).clone();
+                for i in 0..6 {
+                    let pairs = [
+                        (
+                            chunk.get(Vector3::new(5, i, 0)),
+                            right.get(Vector3::new(0, i, 0)),
+                            Direction::Right,
+                        ),
+                        (
+                            chunk.get(Vector3::new(i, 5, 0)),
+                            up.get(Vector3::new(i, 0, 0)),
+                            Direction::Up,
+                        ),
+                    ];
+                    for (tile, neighbour, direction) in pairs {
+                        let tile = tile.expect("Every position has a tile");
+                        let neighbour = neighbour.expect("Every position has a tile");
+                        assert!(tileset.rules.is_allowed(tile, direction, neighbour));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_load_over_several_updates() {
+        let mut world = test_world();
+        assert!(world.update(Vector3::zeros()));
+        assert!(world.visible_chunks.len() < 9);
+        while world.update(Vector3::zeros()) {}
+        assert_eq!(world.visible_chunks.len(), 9);
+
+        // Chunks far behind the camera are dropped
+        let far_away = Vector3::new(600.0, 0.0, 0.0);
+        while world.update(far_away) {}
+        assert_eq!(world.visible_chunks.len(), 9);
+        assert!(!world.chunks.contains_key(&Vector2::new(0, 0)));
+        assert!(world.chunks.len() <= 7 * 7);
+    }
+
+    #[test]
+    fn chunks_keep_count_limits() {
+        let mut tileset = pond_tileset();
+        tileset.count_limits = vec![CountLimit {
+            tiles: (1..7).collect(),
+            min: None,
+            max: Some(Amount::Tiles(0)),
+        }];
+        let config = WfcConfig::builder().map_size(6).seed(9).build().unwrap();
+        let mut world = ChunkedWorld::new(LoadedTileset::without_models(tileset), &config).unwrap();
+
+        for coordinate in [Vector2::new(0, 0), Vector2::new(1, 1)] {
+            assert!(world
+                .chunk(coordinate)
+                .cells()
+                .iter()
+                .all(|tile| *tile == Some(0)));
+        }
+    }
+
+    #[test]
+    fn count_limits_only_count_the_chunk() {
+        let mut tileset = pond_tileset();
+        tileset.count_limits = vec![CountLimit {
+            tiles: vec![0],
+            min: None,
+            max: Some(Amount::Tiles(5)),
+        }];
+        let config = WfcConfig::builder()
+            .map_size(6)
+            .max_backtracks(100)
+            .seed(9)
+            .build()
+            .unwrap();
+        let mut world = ChunkedWorld::new(LoadedTileset::without_models(tileset), &config).unwrap();
+
+        // Grass is the most likely tile, so every chunk reaches the limit with its own tiles
+        for coordinate in [Vector2::new(0, 0), Vector2::new(1, 0), Vector2::new(1, 1)] {
+            let grass = world
+                .chunk(coordinate)
+                .cells()
+                .iter()
+                .filter(|tile| **tile == Some(0))
+                .count();
+            assert_eq!(grass, 5);
+        }
+    }
+}
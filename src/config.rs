@@ -0,0 +1,637 @@
+use std::{fmt, ops::RangeInclusive, path::Path, str::FromStr};
+
+use anyhow::{bail, ensure};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+use crate::{
+    example::ExampleModel,
+    topology::{Boundary, Topology},
+    wave_function_collapse::PlacementStrategy,
+};
+
+const MAP_SIZE_RANGE: RangeInclusive<usize> = 1..=100;
+const MAP_DEPTH_RANGE: RangeInclusive<usize> = 1..=20;
+const MAX_ITERATIONS_RANGE: RangeInclusive<u32> = 100..=10000;
+const PATTERN_SIZE_RANGE: RangeInclusive<usize> = 2..=4;
+const MAX_BACKTRACKS_RANGE: RangeInclusive<u32> = 0..=10000;
+const CHUNK_RADIUS_RANGE: RangeInclusive<usize> = 0..=5;
+const ANIMATION_STEPS_RANGE: RangeInclusive<usize> = 0..=1000;
+
+// Parameters for generating a map, usually read from config.txt
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfcConfig {
+    pub placement_strategy: PlacementStrategy,
+    pub topology: Topology,
+    pub map_width: usize,
+    pub map_height: usize,
+    pub map_depth: usize, // Number of layers stacked on top of each other
+    pub boundary: Boundary,
+    pub border_edge: String, // Edge facing out of the map with the fixed boundary
+    pub chunk_radius: usize, // Chunks shown around the camera in an endless world, 0 generates a single map
+    pub animation_steps: usize, // Collapses shown per frame, 0 only shows the finished map
+    pub max_iterations: u32, // Iterations on top of one for every tile position
+    pub max_backtracks: u32,
+    pub seed: u64, // 0 picks a random seed
+    pub tileset_path: String,
+    pub example_path: String, // Empty uses the edges in tiles_config.txt
+    pub example_model: ExampleModel,
+    pub pattern_size: usize, // Width and height of the patterns taken from the example
+    pub pins_path: String,   // Empty doesn't pin any tiles
+}
+
+impl Default for WfcConfig {
+    fn default() -> Self {
+        Self {
+            placement_strategy: PlacementStrategy::LeastEntropy,
+            topology: Topology::Square,
+            map_width: 10,
+            map_height: 10,
+            map_depth: 1,
+            boundary: Boundary::Open,
+            border_edge: "".to_owned(),
+            chunk_radius: 0,
+            animation_steps: 0,
+            max_iterations: 1000,
+            max_backtracks: 0,
+            seed: 0,
+            tileset_path: "".to_owned(),
+            example_path: "".to_owned(),
+            example_model: ExampleModel::Overlapping,
+            pattern_size: 2,
+            pins_path: "".to_owned(),
+        }
+    }
+}
+
+impl WfcConfig {
+    pub fn builder() -> WfcConfigBuilder {
+        WfcConfigBuilder::default()
+    }
+
+    // Reads a config file in the format of config.txt
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|_| anyhow::format_err!("Could not find config file {}", path.display()))?;
+
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    // Returns: the seed of the config, or a random one when it is 0
+    pub fn pick_seed(&self) -> u64 {
+        match self.seed {
+            0 => usable_seed(self.random().next_u64()),
+            seed => seed,
+        }
+    }
+
+    // Random generator for the map, seeded from the config
+    pub fn random(&self) -> StdRng {
+        if self.seed == 0 {
+            StdRng::from_entropy()
+        } else {
+            StdRng::seed_from_u64(self.seed)
+        }
+    }
+
+    // Source is the name of where the text comes from, used in error messages
+    pub(crate) fn parse(text: &str, source: &str) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        // map_width and map_height override map_size wherever they are in the file
+        let mut map_size = None;
+        let mut map_width = None;
+        let mut map_height = None;
+
+        // Read values
+        for (index, line) in text.lines().enumerate() {
+            // Ignore comments
+            if line.starts_with('#') {
+                continue;
+            }
+            let parts = line
+                .replace(' ', "")
+                .split('=')
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+
+            ensure!(
+                parts.len() == 2 && parts[0].as_str() != "" && parts[1].as_str() != "",
+                format!(
+                    "Error in {} on line {}. The config file accepts lines in the format of KEY=VALUE",
+                    source,
+                    index + 1
+                )
+            );
+
+            // Read and validate options
+            match parts[0].as_str() {
+                "placement_strategy" => {
+                    config.placement_strategy = match parts[1].as_str() {
+                        "least_entropy" => PlacementStrategy::LeastEntropy,
+                        "random" => PlacementStrategy::Random,
+                        "ordered" => PlacementStrategy::Ordered,
+                        "growing" => PlacementStrategy::Growing,
+                        _ => bail!(format!(
+                            "Error in {} on line {}. The placement_strategy can only be least_entropy, random, ordered or growing",
+                            source,
+                            index + 1
+                        )),
+                    }
+                }
+                "topology" => {
+                    config.topology = match parts[1].as_str() {
+                        "square" => Topology::Square,
+                        "hex" => Topology::Hex,
+                        _ => bail!(format!(
+                            "Error in {} on line {}. The topology can only be square or hex",
+                            source,
+                            index + 1
+                        )),
+                    }
+                }
+                "boundary" => {
+                    config.boundary = match parts[1].as_str() {
+                        "open" => Boundary::Open,
+                        "fixed" => Boundary::Fixed,
+                        "wrapping" => Boundary::Wrapping,
+                        _ => bail!(format!(
+                            "Error in {} on line {}. The boundary can only be open, fixed or wrapping",
+                            source,
+                            index + 1
+                        )),
+                    }
+                }
+                "border_edge" => config.border_edge.clone_from(&parts[1]),
+                "tile_set" => config.tileset_path.clone_from(&parts[1]),
+                "example" => config.example_path.clone_from(&parts[1]),
+                "pins" => config.pins_path.clone_from(&parts[1]),
+                "example_model" => {
+                    config.example_model = match parts[1].as_str() {
+                        "overlapping" => ExampleModel::Overlapping,
+                        "tiled" => ExampleModel::Tiled,
+                        _ => bail!(format!(
+                            "Error in {} on line {}. The example_model can only be overlapping or tiled",
+                            source,
+                            index + 1
+                        )),
+                    }
+                }
+                "pattern_size" => {
+                    config.pattern_size =
+                        parse_number(&parts[1], PATTERN_SIZE_RANGE, source, index + 1)?;
+                }
+                "map_size" => {
+                    map_size = Some(parse_number(
+                        &parts[1],
+                        MAP_SIZE_RANGE,
+                        source,
+                        index + 1,
+                    )?);
+                }
+                "map_width" => {
+                    map_width = Some(parse_number(
+                        &parts[1],
+                        MAP_SIZE_RANGE,
+                        source,
+                        index + 1,
+                    )?);
+                }
+                "map_height" => {
+                    map_height = Some(parse_number(
+                        &parts[1],
+                        MAP_SIZE_RANGE,
+                        source,
+                        index + 1,
+                    )?);
+                }
+                "map_depth" => {
+                    config.map_depth =
+                        parse_number(&parts[1], MAP_DEPTH_RANGE, source, index + 1)?;
+                }
+                "chunk_radius" => {
+                    config.chunk_radius =
+                        parse_number(&parts[1], CHUNK_RADIUS_RANGE, source, index + 1)?;
+                }
+                "animation_steps" => {
+                    config.animation_steps =
+                        parse_number(&parts[1], ANIMATION_STEPS_RANGE, source, index + 1)?;
+                }
+                "max_iterations" => {
+                    config.max_iterations =
+                        parse_number(&parts[1], MAX_ITERATIONS_RANGE, source, index + 1)?;
+                }
+                "max_backtracks" => {
+                    config.max_backtracks =
+                        parse_number(&parts[1], MAX_BACKTRACKS_RANGE, source, index + 1)?;
+                }
+                "seed" => {
+                    config.seed = parse_number(&parts[1], 0..=u64::MAX, source, index + 1)?;
+                }
+                _ => bail!(format!(
+                    "Error in {} on line {}. {} is not a option",
+                    source,
+                    index + 1,
+                    parts[0]
+                )),
+            }
+        }
+
+        if let Some(width) = map_width.or(map_size) {
+            config.map_width = width;
+        }
+        if let Some(height) = map_height.or(map_size) {
+            config.map_height = height;
+        }
+
+        // Make sure obligatory options have been set
+        if config.tileset_path.as_str() == "" {
+            bail!(format!(
+                "Error in {}. Option tile_set has not been set",
+                source,
+            ))
+        }
+        config
+            .check_boundary()
+            .and_then(|_| config.check_animation())
+            .map_err(|error| anyhow::format_err!("Error in {}. {}", source, error))?;
+
+        Ok(config)
+    }
+
+    // Fixed boundaries need an edge and wrapping hex maps need an even width
+    fn check_boundary(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.boundary != Boundary::Fixed || !self.border_edge.is_empty(),
+            "The fixed boundary needs a border_edge"
+        );
+        ensure!(
+            self.boundary != Boundary::Wrapping
+                || self.topology != Topology::Hex
+                || self.map_width.is_multiple_of(2),
+            "Hex maps need an even map_width to wrap"
+        );
+        Ok(())
+    }
+
+    // Only single maps are animated, chunks are generated all at once
+    fn check_animation(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.animation_steps == 0 || self.chunk_radius == 0,
+            "animation_steps can't be used together with chunk_radius"
+        );
+        Ok(())
+    }
+}
+
+impl FromStr for WfcConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        Self::parse(text, "config")
+    }
+}
+
+// Writes the config in the format of config.txt, so it can be read again with parse
+impl fmt::Display for WfcConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let placement_strategy = match self.placement_strategy {
+            PlacementStrategy::LeastEntropy => "least_entropy",
+            PlacementStrategy::Random => "random",
+            PlacementStrategy::Ordered => "ordered",
+            PlacementStrategy::Growing => "growing",
+        };
+        let topology = match self.topology {
+            Topology::Square => "square",
+            Topology::Hex => "hex",
+        };
+        let boundary = match self.boundary {
+            Boundary::Open => "open",
+            Boundary::Fixed => "fixed",
+            Boundary::Wrapping => "wrapping",
+        };
+        let example_model = match self.example_model {
+            ExampleModel::Overlapping => "overlapping",
+            ExampleModel::Tiled => "tiled",
+        };
+
+        writeln!(f, "tile_set={}", self.tileset_path)?;
+        writeln!(f, "placement_strategy={}", placement_strategy)?;
+        writeln!(f, "topology={}", topology)?;
+        writeln!(f, "map_width={}", self.map_width)?;
+        writeln!(f, "map_height={}", self.map_height)?;
+        writeln!(f, "map_depth={}", self.map_depth)?;
+        writeln!(f, "boundary={}", boundary)?;
+        writeln!(f, "chunk_radius={}", self.chunk_radius)?;
+        writeln!(f, "animation_steps={}", self.animation_steps)?;
+        writeln!(f, "max_iterations={}", self.max_iterations)?;
+        writeln!(f, "max_backtracks={}", self.max_backtracks)?;
+        writeln!(f, "seed={}", self.seed)?;
+        writeln!(f, "example_model={}", example_model)?;
+        writeln!(f, "pattern_size={}", self.pattern_size)?;
+        // Empty values can't be read, they are the same as leaving the option out
+        for (option, value) in [
+            ("border_edge", &self.border_edge),
+            ("example", &self.example_path),
+            ("pins", &self.pins_path),
+        ] {
+            if !value.is_empty() {
+                writeln!(f, "{}={}", option, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns: the seed, or 1 when it is 0 since a seed of 0 picks a random seed
+pub fn usable_seed(seed: u64) -> u64 {
+    seed.max(1)
+}
+
+fn parse_number<T: FromStr + PartialOrd + std::fmt::Display>(
+    value: &str,
+    range: RangeInclusive<T>,
+    source: &str,
+    line: usize,
+) -> anyhow::Result<T> {
+    let number = value.parse::<T>().map_err(|_| {
+        anyhow::format_err!(
+            "Error in {} on line {}. {} is not a valid number",
+            source,
+            line,
+            value
+        )
+    })?;
+
+    ensure!(
+        range.contains(&number),
+        format!(
+            "Error in {} on line {}. {} is not a accepted number. It has to be between {} and {}",
+            source,
+            line,
+            value,
+            range.start(),
+            range.end()
+        )
+    );
+
+    Ok(number)
+}
+
+#[derive(Default)]
+pub struct WfcConfigBuilder {
+    config: WfcConfig,
+}
+
+impl WfcConfigBuilder {
+    pub fn placement_strategy(mut self, placement_strategy: PlacementStrategy) -> Self {
+        self.config.placement_strategy = placement_strategy;
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> Self {
+        self.config.topology = topology;
+        self
+    }
+
+    // Sets both the width and height
+    pub fn map_size(mut self, map_size: usize) -> Self {
+        self.config.map_width = map_size;
+        self.config.map_height = map_size;
+        self
+    }
+
+    pub fn map_width(mut self, map_width: usize) -> Self {
+        self.config.map_width = map_width;
+        self
+    }
+
+    pub fn map_height(mut self, map_height: usize) -> Self {
+        self.config.map_height = map_height;
+        self
+    }
+
+    pub fn map_depth(mut self, map_depth: usize) -> Self {
+        self.config.map_depth = map_depth;
+        self
+    }
+
+    pub fn boundary(mut self, boundary: Boundary) -> Self {
+        self.config.boundary = boundary;
+        self
+    }
+
+    pub fn border_edge(mut self, border_edge: impl Into<String>) -> Self {
+        self.config.border_edge = border_edge.into();
+        self
+    }
+
+    pub fn chunk_radius(mut self, chunk_radius: usize) -> Self {
+        self.config.chunk_radius = chunk_radius;
+        self
+    }
+
+    pub fn animation_steps(mut self, animation_steps: usize) -> Self {
+        self.config.animation_steps = animation_steps;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: u32) -> Self {
+        self.config.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn max_backtracks(mut self, max_backtracks: u32) -> Self {
+        self.config.max_backtracks = max_backtracks;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn tileset_path(mut self, tileset_path: impl Into<String>) -> Self {
+        self.config.tileset_path = tileset_path.into();
+        self
+    }
+
+    pub fn example_path(mut self, example_path: impl Into<String>) -> Self {
+        self.config.example_path = example_path.into();
+        self
+    }
+
+    pub fn example_model(mut self, example_model: ExampleModel) -> Self {
+        self.config.example_model = example_model;
+        self
+    }
+
+    pub fn pattern_size(mut self, pattern_size: usize) -> Self {
+        self.config.pattern_size = pattern_size;
+        self
+    }
+
+    pub fn pins_path(mut self, pins_path: impl Into<String>) -> Self {
+        self.config.pins_path = pins_path.into();
+        self
+    }
+
+    // Validates the values with the same limits as the config file
+    pub fn build(self) -> anyhow::Result<WfcConfig> {
+        let config = self.config;
+        ensure!(
+            MAP_SIZE_RANGE.contains(&config.map_width),
+            "map_width has to be between {} and {}",
+            MAP_SIZE_RANGE.start(),
+            MAP_SIZE_RANGE.end()
+        );
+        ensure!(
+            MAP_SIZE_RANGE.contains(&config.map_height),
+            "map_height has to be between {} and {}",
+            MAP_SIZE_RANGE.start(),
+            MAP_SIZE_RANGE.end()
+        );
+        ensure!(
+            MAP_DEPTH_RANGE.contains(&config.map_depth),
+            "map_depth has to be between {} and {}",
+            MAP_DEPTH_RANGE.start(),
+            MAP_DEPTH_RANGE.end()
+        );
+        ensure!(
+            MAX_ITERATIONS_RANGE.contains(&config.max_iterations),
+            "max_iterations has to be between {} and {}",
+            MAX_ITERATIONS_RANGE.start(),
+            MAX_ITERATIONS_RANGE.end()
+        );
+        ensure!(
+            MAX_BACKTRACKS_RANGE.contains(&config.max_backtracks),
+            "max_backtracks has to be between {} and {}",
+            MAX_BACKTRACKS_RANGE.start(),
+            MAX_BACKTRACKS_RANGE.end()
+        );
+        ensure!(
+            PATTERN_SIZE_RANGE.contains(&config.pattern_size),
+            "pattern_size has to be between {} and {}",
+            PATTERN_SIZE_RANGE.start(),
+            PATTERN_SIZE_RANGE.end()
+        );
+        ensure!(
+            CHUNK_RADIUS_RANGE.contains(&config.chunk_radius),
+            "chunk_radius has to be between {} and {}",
+            CHUNK_RADIUS_RANGE.start(),
+            CHUNK_RADIUS_RANGE.end()
+        );
+        ensure!(
+            ANIMATION_STEPS_RANGE.contains(&config.animation_steps),
+            "animation_steps has to be between {} and {}",
+            ANIMATION_STEPS_RANGE.start(),
+            ANIMATION_STEPS_RANGE.end()
+        );
+        config.check_boundary()?;
+        config.check_animation()?;
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WfcConfig;
+    use crate::{
+        topology::{Boundary, Topology},
+        wave_function_collapse::PlacementStrategy,
+    };
+
+    #[test]
+    fn parse_config() {
+        let config: WfcConfig =
+            "# comment\nplacement_strategy=growing\ntile_set=./assets/oasis\nmap_size = 20\nseed=5\npins=pins.txt"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            config,
+            WfcConfig::builder()
+                .placement_strategy(PlacementStrategy::Growing)
+                .tileset_path("./assets/oasis")
+                .map_size(20)
+                .seed(5)
+                .pins_path("pins.txt")
+                .build()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_topology() {
+        let config: WfcConfig = "tile_set=a\ntopology=hex".parse().unwrap();
+
+        assert_eq!(config.topology, Topology::Hex);
+        assert!("tile_set=a\ntopology=triangle"
+            .parse::<WfcConfig>()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_boundary() {
+        let config: WfcConfig = "tile_set=a\nboundary=fixed\nborder_edge=water"
+            .parse()
+            .unwrap();
+
+        assert_eq!(config.boundary, Boundary::Fixed);
+        assert_eq!(config.border_edge, "water");
+        assert!("tile_set=a\nboundary=fixed".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nboundary=wrapping\ntopology=hex\nmap_width=9"
+            .parse::<WfcConfig>()
+            .is_err());
+        assert!(WfcConfig::builder()
+            .boundary(Boundary::Wrapping)
+            .topology(Topology::Hex)
+            .map_size(10)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn parse_rectangular_map() {
+        let config: WfcConfig = "tile_set=a\nmap_size=20\nmap_width=80\nmap_height=8"
+            .parse()
+            .unwrap();
+
+        assert_eq!((config.map_width, config.map_height), (80, 8));
+
+        let config: WfcConfig = "tile_set=a\nmap_width=80\nmap_size=20".parse().unwrap();
+        assert_eq!((config.map_width, config.map_height), (80, 20));
+    }
+
+    #[test]
+    fn written_config_reads_the_same() {
+        let config = WfcConfig::builder()
+            .placement_strategy(PlacementStrategy::Ordered)
+            .topology(Topology::Hex)
+            .map_width(12)
+            .map_height(7)
+            .boundary(Boundary::Fixed)
+            .border_edge("water")
+            .seed(42)
+            .tileset_path("./assets/oasis")
+            .build()
+            .unwrap();
+
+        assert_eq!(config.to_string().parse::<WfcConfig>().unwrap(), config);
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!("map_size=10".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nmap_size=0".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nunknown=1".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nmap_depth=21".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nchunk_radius=6".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nchunk_radius=1\nanimation_steps=5"
+            .parse::<WfcConfig>()
+            .is_err());
+        assert!("tile_set=a\npattern_size=1".parse::<WfcConfig>().is_err());
+        assert!("tile_set=a\nexample_model=x".parse::<WfcConfig>().is_err());
+        assert!(WfcConfig::builder().max_iterations(5).build().is_err());
+    }
+}
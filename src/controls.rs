@@ -24,6 +24,7 @@ pub enum Action {
     CameraRotateDown,
     DragMouse,
     Enter,
+    SaveMap,
 
     //Window
     CloseWindow,
@@ -133,6 +134,11 @@ impl Default for Controls {
                 ModifiersState::empty(),
                 Action::Enter,
             ),
+            Binding::new(
+                Character(SmolStr::new("s")),
+                ModifiersState::CONTROL,
+                Action::SaveMap,
+            ),
         ];
 
         Self {
@@ -179,9 +185,8 @@ impl Controls {
         mods: &ModifiersState,
         is_pressed: bool,
     ) -> Option<Action> {
-        self.parse_key_binding(key, mods).map(|action| {
-            control_state.action_updated(&action, is_pressed);
-            action
+        self.parse_key_binding(key, mods).inspect(|action| {
+            control_state.action_updated(action, is_pressed);
         })
     }
 
@@ -205,9 +210,8 @@ impl Controls {
         mods: &ModifiersState,
         is_pressed: bool,
     ) -> Option<Action> {
-        self.parse_mouse_binding(button, mods).map(|action| {
-            control_state.action_updated(&action, is_pressed);
-            action
+        self.parse_mouse_binding(button, mods).inspect(|action| {
+            control_state.action_updated(action, is_pressed);
         })
     }
 
@@ -0,0 +1,99 @@
+use anyhow::ensure;
+use nalgebra::Vector3;
+
+use crate::grid::Grid;
+
+// How the tiles are learned from an example map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExampleModel {
+    Overlapping, // Patterns of several tiles that have to overlap, see overlapping_model
+    Tiled,       // Single tiles that can be next to each other like in the example
+}
+
+// Cell of an example map, written as model or model@rotation
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ExampleTile {
+    pub model: String,
+    pub rotation: usize,
+}
+
+// Reads an example map where each line is a row of comma separated tiles, the first line being the top row
+pub fn load_example(path: &str) -> anyhow::Result<Grid<ExampleTile>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|_| anyhow::format_err!("Could not find example file {}", path))?;
+
+    parse_example(&text, path)
+}
+
+// Source is the name of where the text comes from, used in error messages
+pub fn parse_example(text: &str, source: &str) -> anyhow::Result<Grid<ExampleTile>> {
+    let mut rows: Vec<Vec<ExampleTile>> = vec![];
+    for (index, line) in text.lines().enumerate() {
+        // Ignore comments and empty lines
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let mut row = vec![];
+        for value in line.replace(' ', "").split(',') {
+            let parts = value.split('@').collect::<Vec<&str>>();
+            ensure!(
+                parts.len() <= 2 && !parts[0].is_empty(),
+                format!(
+                    "Error in {} on line {}. Tiles are written as model or model@rotation",
+                    source,
+                    index + 1
+                )
+            );
+            let rotation = match parts.get(1) {
+                Some(rotation) => rotation.parse::<usize>().map_err(|_| {
+                    anyhow::format_err!(
+                        "Error in {} on line {}. The rotation can only be a non negative integer",
+                        source,
+                        index + 1
+                    )
+                })?,
+                None => 0,
+            };
+            row.push(ExampleTile {
+                model: parts[0].to_owned(),
+                rotation,
+            });
+        }
+
+        ensure!(
+            rows.is_empty() || rows[0].len() == row.len(),
+            format!(
+                "Error in {} on line {}. All rows need the same number of tiles",
+                source,
+                index + 1
+            )
+        );
+        rows.push(row);
+    }
+    ensure!(
+        !rows.is_empty(),
+        format!("{} does not contain any tiles", source)
+    );
+
+    // The grid starts with the bottom row
+    let size = Vector3::new(rows[0].len(), rows.len(), 1);
+    Ok(Grid::new(size, rows.into_iter().rev().flatten().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_example;
+
+    #[test]
+    fn parse_example_rows() {
+        let example = parse_example("# comment\na, b@1\nc, d", "test").unwrap();
+
+        assert_eq!((example.width(), example.height()), (2, 2));
+        // The last line is the bottom row
+        assert_eq!(example.cells()[0].model, "c");
+        assert_eq!(example.cells()[3].rotation, 1);
+        assert!(parse_example("a, b\nc", "test").is_err());
+        assert!(parse_example("a, b@x", "test").is_err());
+    }
+}
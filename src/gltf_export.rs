@@ -0,0 +1,294 @@
+use std::{collections::HashMap, path::Path};
+
+use nalgebra::Vector3;
+
+use crate::{scene::Scene, tile_data::TileId, Color, Triangle};
+
+// Types and targets from the glTF specification
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+const JSON_CHUNK: u32 = 0x4E4F534A;
+const BIN_CHUNK: u32 = 0x004E4942;
+
+// Writes the scene to a binary glTF file, see to_glb
+pub fn save_glb(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    std::fs::write(&path, to_glb(scene))
+        .map_err(|error| anyhow::format_err!("Could not write {:?}: {}", path.as_ref(), error))
+}
+
+// Converts the scene to a binary glTF file, the reverse of ModelLoader::load_gltf_model.
+// Every tile placed with Scene::instantiate_grid becomes a node that is moved to its position and
+// uses the mesh of its tile, which is shared by all nodes of the same tile. The other triangles are
+// put in a single node, translucent triangles are left out. Every color gets its own material.
+pub fn to_glb(scene: &Scene) -> Vec<u8> {
+    let mut builder = GlbBuilder::default();
+    let mut nodes = Vec::new();
+
+    let mut tile_meshes = HashMap::<TileId, usize>::new();
+    let mut exported = vec![false; scene.triangles.len()];
+    for instance in &scene.tiles {
+        if instance.triangles.is_empty() {
+            continue;
+        }
+        let triangles = &scene.triangles[instance.triangles.clone()];
+        exported[instance.triangles.clone()].fill(true);
+
+        // The triangles were moved to the position of the tile when they were placed
+        let mesh = *tile_meshes.entry(instance.tile).or_insert_with(|| {
+            let model = triangles
+                .iter()
+                .map(|triangle| triangle.translate(-instance.position))
+                .collect::<Vec<Triangle>>();
+            builder.add_mesh(&format!("Tile {}", instance.tile), &model)
+        });
+        nodes.push(format!(
+            "{{\"name\":\"Tile {}\",\"mesh\":{},\"translation\":{}}}",
+            instance.tile,
+            mesh,
+            json_vector(instance.position)
+        ));
+    }
+
+    let other_triangles = scene
+        .triangles
+        .iter()
+        .zip(exported)
+        .filter(|(_, exported)| !exported)
+        .map(|(triangle, _)| *triangle)
+        .collect::<Vec<Triangle>>();
+    if !other_triangles.is_empty() {
+        let mesh = builder.add_mesh("Other", &other_triangles);
+        nodes.push(format!("{{\"name\":\"Other\",\"mesh\":{}}}", mesh));
+    }
+
+    builder.finish(&nodes)
+}
+
+// Collects the meshes, materials and binary data of a glTF file
+#[derive(Default)]
+struct GlbBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    material_indices: HashMap<[u32; 3], usize>, // Indexed by the bits of the color
+}
+
+impl GlbBuilder {
+    // Adds a mesh with a primitive for every color of the triangles
+    // Returns: the index of the mesh
+    fn add_mesh(&mut self, name: &str, triangles: &[Triangle]) -> usize {
+        let mut primitives = Vec::<(usize, Vec<Triangle>)>::new();
+        let mut primitive_indices = HashMap::<usize, usize>::new();
+        for triangle in triangles {
+            let material = self.material(triangle.color);
+            let index = *primitive_indices.entry(material).or_insert_with(|| {
+                primitives.push((material, Vec::new()));
+                primitives.len() - 1
+            });
+            primitives[index].1.push(*triangle);
+        }
+
+        let primitives = primitives
+            .iter()
+            .map(|(material, triangles)| {
+                let positions = triangles
+                    .iter()
+                    .flat_map(|triangle| [triangle.v0, triangle.v1, triangle.v2])
+                    .collect::<Vec<Vector3<f32>>>();
+                // Triangle normals point away from the side glTF sees as the front
+                let normals = triangles
+                    .iter()
+                    .flat_map(|triangle| [-triangle.normal; 3])
+                    .collect::<Vec<Vector3<f32>>>();
+                format!(
+                    "{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{}}},\"material\":{}}}",
+                    self.add_accessor(&positions, true),
+                    self.add_accessor(&normals, false),
+                    material
+                )
+            })
+            .collect::<Vec<String>>();
+
+        self.meshes.push(format!(
+            "{{\"name\":\"{}\",\"primitives\":[{}]}}",
+            name,
+            primitives.join(",")
+        ));
+        self.meshes.len() - 1
+    }
+
+    // Returns: the index of the material with the color as base color, added if it is new
+    fn material(&mut self, color: Color) -> usize {
+        let key = [
+            color.0.x.to_bits(),
+            color.0.y.to_bits(),
+            color.0.z.to_bits(),
+        ];
+        if let Some(index) = self.material_indices.get(&key) {
+            return *index;
+        }
+
+        let index = self.materials.len();
+        self.materials.push(format!(
+            "{{\"name\":\"Color {}\",\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{},{},{},1],\"metallicFactor\":0}}}}",
+            index, color.0.x, color.0.y, color.0.z
+        ));
+        self.material_indices.insert(key, index);
+        index
+    }
+
+    // Adds the vectors to the binary data, positions also need their bounds
+    // Returns: the index of the accessor
+    fn add_accessor(&mut self, values: &[Vector3<f32>], bounds: bool) -> usize {
+        let offset = self.buffer.len();
+        for value in values {
+            for component in value.iter() {
+                self.buffer.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        self.buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+            offset,
+            self.buffer.len() - offset,
+            ARRAY_BUFFER
+        ));
+
+        let bounds = match bounds {
+            true => {
+                let min = values.iter().fold(Vector3::repeat(f32::MAX), |min, value| {
+                    min.zip_map(value, f32::min)
+                });
+                let max = values.iter().fold(Vector3::repeat(f32::MIN), |max, value| {
+                    max.zip_map(value, f32::max)
+                });
+                format!(",\"min\":{},\"max\":{}", json_vector(min), json_vector(max))
+            }
+            false => String::new(),
+        };
+        self.accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\"{}}}",
+            self.buffer_views.len() - 1,
+            FLOAT,
+            values.len(),
+            bounds
+        ));
+        self.accessors.len() - 1
+    }
+
+    // Returns: the file with a scene of the nodes
+    fn finish(self, nodes: &[String]) -> Vec<u8> {
+        let scene_nodes = (0..nodes.len())
+            .map(|node| node.to_string())
+            .collect::<Vec<String>>();
+        let mut json = format!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"{}\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}],\"nodes\":[{}],\"meshes\":[{}],\"materials\":[{}],\"accessors\":[{}],\"bufferViews\":[{}]",
+            env!("CARGO_PKG_NAME"),
+            scene_nodes.join(","),
+            nodes.join(","),
+            self.meshes.join(","),
+            self.materials.join(","),
+            self.accessors.join(","),
+            self.buffer_views.join(",")
+        );
+        // A buffer can't be empty
+        if !self.buffer.is_empty() {
+            json.push_str(&format!(
+                ",\"buffers\":[{{\"byteLength\":{}}}]",
+                self.buffer.len()
+            ));
+        }
+        json.push('}');
+
+        // Chunks are padded to 4 bytes, with spaces for JSON and zeros for binary data
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut buffer = self.buffer;
+        buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+        let mut chunks = vec![(JSON_CHUNK, json)];
+        if !buffer.is_empty() {
+            chunks.push((BIN_CHUNK, buffer));
+        }
+        let length = 12 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        for (chunk_type, data) in chunks {
+            glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            glb.extend_from_slice(&chunk_type.to_le_bytes());
+            glb.extend_from_slice(&data);
+        }
+        glb
+    }
+}
+
+fn json_vector(vector: Vector3<f32>) -> String {
+    format!("[{},{},{}]", vector.x, vector.y, vector.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Rotation3, Vector3};
+
+    use super::to_glb;
+    use crate::{
+        grid::Grid, model_loader::ModelLoader, scene::Scene, topology::Topology, Color, Triangle,
+    };
+
+    #[test]
+    fn exported_scene_loads_again() {
+        let triangle = |color: Color| {
+            Triangle::new(
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.5, 0.0, 0.0),
+                Vector3::new(0.0, 0.5, 0.0),
+                color,
+            )
+        };
+        let models = vec![
+            vec![triangle(Color::RED), triangle(Color::BLUE)],
+            vec![],
+            vec![triangle(Color::GREEN)],
+        ];
+        let grid = Grid::new(
+            Vector3::new(2, 2, 1),
+            vec![Some(0), Some(1), Some(2), Some(0)],
+        );
+        let mut scene = Scene::new();
+        scene.instantiate_grid(&grid, &models, Topology::Square, Vector3::zeros());
+        scene.instantiate_model(&[triangle(Color::WHITE)], Vector3::new(0.0, 0.0, 3.0));
+        scene.instantiate_box(Vector3::zeros(), 1.0, Color::WHITE, 0.5);
+
+        let glb = to_glb(&scene);
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(glb.len() % 4, 0);
+        let json = String::from_utf8_lossy(&glb[20..]);
+        // The two red and blue tiles share a mesh, the empty tile has no node
+        assert_eq!(json.matches("\"mesh\":0").count(), 2);
+        assert_eq!(json.matches("\"name\":\"Other\",\"mesh\":2").count(), 1);
+        assert_eq!(json.matches("\"baseColorFactor\"").count(), 4);
+
+        let path = std::env::temp_dir().join("gltf_export_test.glb");
+        std::fs::write(&path, &glb).unwrap();
+        let loaded = ModelLoader::new()
+            .load_gltf_model(&path, Rotation3::identity())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let key = |triangle: &Triangle| {
+            format!(
+                "{:.3?} {:.3?} {:.3?} {:?}",
+                triangle.v0, triangle.v1, triangle.v2, triangle.color
+            )
+        };
+        let mut expected = scene.triangles.iter().map(key).collect::<Vec<String>>();
+        let mut loaded = loaded.iter().map(key).collect::<Vec<String>>();
+        expected.sort();
+        loaded.sort();
+        assert_eq!(loaded, expected);
+    }
+}
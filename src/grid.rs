@@ -0,0 +1,58 @@
+use nalgebra::Vector3;
+
+// Map of values with one or more layers. Each layer is stored row by row starting in the
+// bottom left corner, starting with the lowest layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    size: Vector3<usize>, // Width, height and depth
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(size: Vector3<usize>, cells: Vec<T>) -> Self {
+        assert_eq!(
+            cells.len(),
+            size.x * size.y * size.z,
+            "Grid needs width * height * depth cells"
+        );
+        Self { size, cells }
+    }
+
+    pub fn size(&self) -> Vector3<usize> {
+        self.size
+    }
+
+    pub fn width(&self) -> usize {
+        self.size.x
+    }
+
+    pub fn height(&self) -> usize {
+        self.size.y
+    }
+
+    pub fn depth(&self) -> usize {
+        self.size.z
+    }
+
+    pub fn get(&self, position: Vector3<usize>) -> &T {
+        &self.cells[(position.z * self.size.y + position.y) * self.size.x + position.x]
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    // Iterates over all cells together with their position
+    pub fn iter(&self) -> impl Iterator<Item = (Vector3<usize>, &T)> {
+        self.cells.iter().enumerate().map(|(index, cell)| {
+            (
+                Vector3::new(
+                    index % self.size.x,
+                    index / self.size.x % self.size.y,
+                    index / (self.size.x * self.size.y),
+                ),
+                cell,
+            )
+        })
+    }
+}
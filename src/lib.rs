@@ -1,14 +1,36 @@
 use easy_gltf::model::Vertex;
-use nalgebra::{Matrix3, Rotation3, Vector2, Vector3};
+use nalgebra::{Matrix3, Rotation3, Vector3};
 
+pub mod adjacency;
+pub mod animated_map;
+pub mod batch;
 pub mod camera;
+pub mod cell_selector;
+pub mod chunks;
+pub mod config;
+#[cfg(feature = "viewer")]
 pub mod controls;
+pub mod example;
+pub mod gltf_export;
+pub mod grid;
+pub mod map_output;
 pub mod model_loader;
+pub mod obj_export;
+pub mod overlapping_model;
+pub mod pins;
+#[cfg(feature = "viewer")]
 pub mod renderers;
+pub mod saved_map;
 pub mod scene;
+#[cfg(test)]
+mod test_tiles;
 pub mod tile;
+pub mod tile_count;
 pub mod tile_data;
+pub mod tileset;
+pub mod topology;
 pub mod wave_function_collapse;
+pub mod weight_map;
 
 pub struct Ray {
     pub origin: Vector3<f32>,
@@ -93,6 +115,17 @@ impl Triangle {
         )
     }
 
+    // Mirrors the triangle left to right, swapping two vertices keeps it facing outwards
+    fn mirror(&self) -> Triangle {
+        let mirror = |vertex: Vector3<f32>| Vector3::new(-vertex.x, vertex.y, vertex.z);
+        Triangle::new(
+            mirror(self.v0),
+            mirror(self.v2),
+            mirror(self.v1),
+            self.color,
+        )
+    }
+
     fn calculate_normal(v0: Vector3<f32>, v1: Vector3<f32>, v2: Vector3<f32>) -> Vector3<f32> {
         let e1 = v1 - v0;
         let e2 = v2 - v0;
@@ -174,6 +207,7 @@ impl Color {
         Color(Vector3::new(vector.x, vector.y, vector.z))
     }
 
+    #[cfg(feature = "viewer")]
     #[inline(always)]
     pub fn to_sdl(&self) -> sdl2::pixels::Color {
         sdl2::pixels::Color::RGB(
@@ -184,39 +218,53 @@ impl Color {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+// Edges of a tile. Square tiles use up, right, down and left, hex tiles use up, down and the
+// four diagonals. Top and bottom are the layers above and below
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Direction {
     Up,
+    UpRight,
     Right,
+    DownRight,
     Down,
+    DownLeft,
     Left,
+    UpLeft,
+    Top,
+    Bottom,
 }
 
 impl Direction {
-    pub fn get_vector(&self) -> Vector2<i32> {
-        match self {
-            Direction::Up => Vector2::new(0, 1),
-            Direction::Right => Vector2::new(1, 0),
-            Direction::Down => Vector2::new(0, -1),
-            Direction::Left => Vector2::new(-1, 0),
-        }
-    }
+    pub const COUNT: usize = 10;
 
     pub fn get_opposite(&self) -> Direction {
         match self {
             Direction::Up => Direction::Down,
+            Direction::UpRight => Direction::DownLeft,
             Direction::Right => Direction::Left,
+            Direction::DownRight => Direction::UpLeft,
             Direction::Down => Direction::Up,
+            Direction::DownLeft => Direction::UpRight,
             Direction::Left => Direction::Right,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::Top => Direction::Bottom,
+            Direction::Bottom => Direction::Top,
         }
     }
 
+    // Every direction of every topology, see Topology::iterator for the ones a tile has
     pub fn iterator() -> impl Iterator<Item = Direction> {
         [
             Direction::Up,
+            Direction::UpRight,
             Direction::Right,
+            Direction::DownRight,
             Direction::Down,
+            Direction::DownLeft,
             Direction::Left,
+            Direction::UpLeft,
+            Direction::Top,
+            Direction::Bottom,
         ]
         .iter()
         .copied()
@@ -4,7 +4,8 @@ use kth_dd2323_project::renderers::raster_renderer::{
 };
 use kth_dd2323_project::renderers::renderer::Renderer;
 use kth_dd2323_project::{
-    camera::Camera, controls::ControlState, scene::Scene, wave_function_collapse::WFC,
+    animated_map::AnimatedMap, batch, camera::Camera, chunks::ChunkedWorld, config::WfcConfig,
+    controls::ControlState, saved_map::SavedMap, scene::Scene, wave_function_collapse,
 };
 use nalgebra::{Vector2, Vector3};
 use std::error::Error;
@@ -53,22 +54,97 @@ fn main() -> anyhow::Result<()> {
         Vector3::new(5.0, -8.0, 8.0),
         Vector3::new(0.0, 2.0, -1.0),
     );
-    let scene = setup_scene()?;
+    let (scene, world) = match load_path()? {
+        Some(path) => {
+            let map = SavedMap::load(path)?;
+            (map.scene()?, World::Map(Box::new(map)))
+        }
+        None => setup_scene()?,
+    };
 
-    let mut state = pollster::block_on(Application::new(&instance, scene, camera));
+    let mut state = pollster::block_on(Application::new(&instance, scene, world, camera));
 
     event_loop.run_app(&mut state)?;
 
     Ok(())
 }
 
-fn setup_scene() -> anyhow::Result<Scene> {
-    let mut scene = Scene::new();
-    let mut wfc = WFC::new(&mut scene);
+// What the scene shows
+enum World {
+    Map(Box<SavedMap>),         // A single map that is generated at once
+    Chunks(Box<ChunkedWorld>),  // The chunks around the camera when chunk_radius is set
+    Animated(Box<AnimatedMap>), // A map that is still being generated when animation_steps is set
+}
+
+fn setup_scene() -> anyhow::Result<(Scene, World)> {
+    // Read the config every time so changes are used when regenerating the world
+    let config = WfcConfig::load("./config.txt")?;
+    if config.chunk_radius > 0 {
+        return Ok((
+            Scene::new(),
+            World::Chunks(Box::new(ChunkedWorld::load(&config)?)),
+        ));
+    }
+    if config.animation_steps > 0 {
+        let animated_map = AnimatedMap::load(&config)?;
+        return Ok((
+            animated_map.scene(),
+            World::Animated(Box::new(animated_map)),
+        ));
+    }
+
+    let seed_count = seed_count()?;
+    if seed_count > 1 {
+        let (scene, map, best) = batch::generate_best_scene(&config, seed_count)?;
+        info!(
+            "Showing seed {}, which filled {:.1}% of the map",
+            best.seed,
+            best.score * 100.0
+        );
+        return Ok((scene, World::Map(Box::new(map))));
+    }
+    let (scene, map) = wave_function_collapse::generate_map(&config)?;
+    Ok((scene, World::Map(Box::new(map))))
+}
+
+// Reads the --load PATH argument, which shows a saved map instead of generating one
+fn load_path() -> anyhow::Result<Option<String>> {
+    let arguments = std::env::args().collect::<Vec<String>>();
+    let Some(index) = arguments.iter().position(|argument| argument == "--load") else {
+        return Ok(None);
+    };
+    arguments
+        .get(index + 1)
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| anyhow::format_err!("--load needs the path of a saved map"))
+}
 
-    wfc.place_tiles()?;
+// Saves the shown map next to config.txt, named after its seed
+fn save_map(world: &World) {
+    let World::Map(map) = world else {
+        info!("Only single maps that are generated at once can be saved");
+        return;
+    };
+    let path = format!("./map_{}.txt", map.config.seed);
+    match map.save(&path) {
+        Ok(()) => info!("Saved the map to {}", path),
+        Err(err) => error!("{err}"),
+    }
+}
 
-    Ok(scene)
+// Reads the --seeds N argument, which generates N maps at the same time and shows the most
+// filled one. Without it a single map is generated
+fn seed_count() -> anyhow::Result<usize> {
+    let arguments = std::env::args().collect::<Vec<String>>();
+    let Some(index) = arguments.iter().position(|argument| argument == "--seeds") else {
+        return Ok(1);
+    };
+    arguments
+        .get(index + 1)
+        .and_then(|count| count.parse::<usize>().ok())
+        .filter(|count| *count > 0)
+        .ok_or_else(|| anyhow::format_err!("--seeds needs a number of seeds larger than 0"))
 }
 
 fn update(
@@ -78,14 +154,22 @@ fn update(
     enter_clicked: &mut bool,
     camera: &mut Camera,
     scene: &mut Scene,
+    world: &mut World,
 ) {
     move_camera(dt, state, mouse_reference_position, camera);
 
     // Regenerate world
     if state.enter && !*enter_clicked {
-        *scene = setup_scene().expect("Scene not null");
+        (*scene, *world) = setup_scene().expect("Scene not null");
     }
     *enter_clicked = state.enter;
+
+    // Generate the chunks the camera has moved close to
+    if let World::Chunks(chunks) = world {
+        if chunks.update(camera.position) {
+            *scene = chunks.scene();
+        }
+    }
 }
 
 // Move and rotate camera
@@ -168,6 +252,7 @@ struct Application<'window> {
     controls: Controls,
 
     scene: Scene,
+    world: World,
     camera: Camera,
 
     last_update: std::time::Instant,
@@ -177,13 +262,19 @@ struct Application<'window> {
 }
 
 impl<'window> Application<'window> {
-    async fn new(instance: &'window wgpu::Instance, scene: Scene, camera: Camera) -> Self {
+    async fn new(
+        instance: &'window wgpu::Instance,
+        scene: Scene,
+        world: World,
+        camera: Camera,
+    ) -> Self {
         Self {
             window: None,
             instance,
             control_state: ControlState::default(),
             controls: Controls::default(),
             scene,
+            world,
             camera,
             mouse_reference_position: None,
             last_update: std::time::Instant::now(),
@@ -231,6 +322,7 @@ impl<'window> Application<'window> {
             Action::DragResizeWindow => window.drag_resize_window(),
             //Action::PrintHelp => self.print_help(),
             Action::RequestResize => window.swap_dimensions(),
+            Action::SaveMap => save_map(&self.world),
             _ => (),
         }
     }
@@ -294,6 +386,7 @@ impl ApplicationHandler<ExternalEvent> for Application<'_> {
             &mut self.enter_clicked,
             &mut self.camera,
             &mut self.scene,
+            &mut self.world,
         );
 
         self.last_update = std::time::Instant::now();
@@ -341,8 +434,16 @@ impl ApplicationHandler<ExternalEvent> for Application<'_> {
                     &mut self.enter_clicked,
                     &mut self.camera,
                     &mut self.scene,
+                    &mut self.world,
                 );
 
+                // Take the next steps of the map that is being generated, once every frame
+                if let World::Animated(animated_map) = &mut self.world {
+                    if animated_map.update() {
+                        self.scene = animated_map.scene();
+                    }
+                }
+
                 if let Err(err) = window.draw(&self.scene, &self.camera) {
                     error!("Error drawing window: {err}");
                 }
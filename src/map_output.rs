@@ -0,0 +1,221 @@
+use anyhow::ensure;
+use nalgebra::Vector3;
+
+use crate::{grid::Grid, tile_data::TileId, tileset::Tileset, topology::Topology};
+
+// Written where no tile could be placed
+const MISSING_TILE: &str = "?";
+
+// Writes a generated map in the format of an example map, see example::parse_example.
+// Each row is a line of comma separated tile names starting with the top row, maps with several
+// layers start each layer with a comment and are written from the bottom layer up
+pub fn to_text(grid: &Grid<Option<TileId>>, tileset: &Tileset) -> String {
+    names_to_text(&tile_names(grid, tileset))
+}
+
+// Writes a map of tile names like to_text
+pub fn names_to_text(names: &Grid<Option<String>>) -> String {
+    let mut text = String::new();
+    for z in 0..names.depth() {
+        if names.depth() > 1 {
+            text.push_str(&format!("# Layer {}\n", z));
+        }
+        for row in rows(names, z) {
+            let row = row
+                .iter()
+                .map(|name| name.unwrap_or(MISSING_TILE))
+                .collect::<Vec<&str>>();
+            text.push_str(&row.join(", "));
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// Reads a map written by names_to_text, size is the size of the map
+// Source is the name of where the text comes from, used in error messages
+pub fn parse_names(
+    text: &str,
+    size: Vector3<usize>,
+    source: &str,
+) -> anyhow::Result<Grid<Option<String>>> {
+    let lines = text
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .collect::<Vec<&str>>();
+    ensure!(
+        lines.len() == size.y * size.z,
+        "{} has {} rows of tiles, a map of size {}x{}x{} needs {}",
+        source,
+        lines.len(),
+        size.x,
+        size.y,
+        size.z,
+        size.y * size.z
+    );
+
+    let mut cells = vec![None; size.x * size.y * size.z];
+    for (index, line) in lines.iter().enumerate() {
+        let row = line.split(',').map(str::trim).collect::<Vec<&str>>();
+        ensure!(
+            row.len() == size.x,
+            "Row {} of {} has {} tiles instead of {}",
+            index + 1,
+            source,
+            row.len(),
+            size.x
+        );
+        // Rows start with the top row of the bottom layer
+        let (z, y) = (index / size.y, size.y - 1 - index % size.y);
+        for (x, name) in row.into_iter().enumerate() {
+            if name != MISSING_TILE {
+                cells[x + y * size.x + z * size.x * size.y] = Some(name.to_owned());
+            }
+        }
+    }
+    Ok(Grid::new(size, cells))
+}
+
+// Writes a generated map as a JSON object with the tileset, seed and size of the map. Layers
+// are written from the bottom layer up and rows from the top row down like in to_text,
+// null is written where no tile could be placed
+pub fn to_json(grid: &Grid<Option<TileId>>, tileset: &Tileset, seed: u64) -> String {
+    let topology = match tileset.topology {
+        Topology::Square => "square",
+        Topology::Hex => "hex",
+    };
+    let names = tile_names(grid, tileset);
+    let layers = (0..grid.depth())
+        .map(|z| {
+            let rows = rows(&names, z)
+                .iter()
+                .map(|row| {
+                    let row = row
+                        .iter()
+                        .map(|name| name.map_or("null".to_owned(), json_string))
+                        .collect::<Vec<String>>();
+                    format!("      [{}]", row.join(", "))
+                })
+                .collect::<Vec<String>>();
+            format!("    [\n{}\n    ]", rows.join(",\n"))
+        })
+        .collect::<Vec<String>>();
+
+    format!(
+        "{{\n  \"tileset\": {},\n  \"topology\": \"{}\",\n  \"seed\": {},\n  \"width\": {},\n  \"height\": {},\n  \"depth\": {},\n  \"layers\": [\n{}\n  ]\n}}\n",
+        json_string(&tileset.path),
+        topology,
+        seed,
+        grid.width(),
+        grid.height(),
+        grid.depth(),
+        layers.join(",\n")
+    )
+}
+
+// Returns: the name of every tile, see TileData::unique_name
+pub fn tile_names(grid: &Grid<Option<TileId>>, tileset: &Tileset) -> Grid<Option<String>> {
+    Grid::new(
+        grid.size(),
+        grid.cells()
+            .iter()
+            .map(|tile| tile.map(|tile| tileset.tiles[tile].unique_name()))
+            .collect(),
+    )
+}
+
+// Returns: the names in a layer, starting with the top row
+fn rows(names: &Grid<Option<String>>, z: usize) -> Vec<Vec<Option<&str>>> {
+    (0..names.height())
+        .rev()
+        .map(|y| {
+            (0..names.width())
+                .map(|x| names.get(Vector3::new(x, y, z)).as_deref())
+                .collect()
+        })
+        .collect()
+}
+
+fn json_string(value: &str) -> String {
+    let mut string = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            character if character.is_control() => {
+                string.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => string.push(character),
+        }
+    }
+    string.push('"');
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::{names_to_text, parse_names, tile_names, to_json, to_text};
+    use crate::{grid::Grid, test_tiles::named_tile as tile, tileset::Tileset, topology::Topology};
+
+    #[test]
+    fn text_and_json_output() {
+        let mut tileset = Tileset::new(
+            vec![tile("grass.glb", 0), tile("road.glb", 1)],
+            Topology::Square,
+        );
+        tileset.path = "./assets/\"city\"".to_owned();
+        // The bottom row is written last
+        let grid = Grid::new(Vector3::new(2, 2, 1), vec![Some(0), None, Some(1), Some(0)]);
+
+        assert_eq!(
+            to_text(&grid, &tileset),
+            "road.glb@1, grass.glb@0\ngrass.glb@0, ?\n"
+        );
+        assert_eq!(
+            to_json(&grid, &tileset, 7),
+            r#"{
+  "tileset": "./assets/\"city\"",
+  "topology": "square",
+  "seed": 7,
+  "width": 2,
+  "height": 2,
+  "depth": 1,
+  "layers": [
+    [
+      ["road.glb@1", "grass.glb@0"],
+      ["grass.glb@0", null]
+    ]
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn read_written_names() {
+        let names = Grid::new(
+            Vector3::new(2, 1, 2),
+            vec![
+                Some("a@0".to_owned()),
+                None,
+                Some("b@1m".to_owned()),
+                Some("a@3".to_owned()),
+            ],
+        );
+        let text = names_to_text(&names);
+        assert_eq!(text, "# Layer 0\na@0, ?\n# Layer 1\nb@1m, a@3\n");
+        assert_eq!(parse_names(&text, names.size(), "test").unwrap(), names);
+        assert!(parse_names(&text, Vector3::new(2, 2, 2), "test").is_err());
+        assert!(parse_names("a, b, c\nd, e", Vector3::new(2, 2, 1), "test").is_err());
+
+        let tileset = Tileset::new(vec![Default::default()], Topology::Square);
+        let grid = Grid::new(Vector3::new(2, 1, 1), vec![Some(0), None]);
+        assert_eq!(
+            tile_names(&grid, &tileset).cells(),
+            [Some(tileset.tiles[0].unique_name()), None]
+        );
+    }
+}
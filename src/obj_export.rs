@@ -0,0 +1,132 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{scene::Scene, Color, Triangle};
+
+// Writes the scene to a Wavefront OBJ file and its materials to an MTL file next to it with the
+// same name, see to_obj
+pub fn save_obj(scene: &Scene, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::format_err!("{:?} is not a file name", path))?;
+
+    let (obj, mtl) = to_obj(scene, mtl_name);
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents)
+            .map_err(|error| anyhow::format_err!("Could not write {:?}: {}", path, error))
+    };
+    write(path, obj)?;
+    write(&mtl_path, mtl)
+}
+
+// Converts the opaque triangles of the scene to a Wavefront OBJ file, which reads its materials
+// from the MTL file called mtl_name. Every color of the triangles gets its own material.
+// Returns: the contents of the OBJ file and the MTL file
+pub fn to_obj(scene: &Scene, mtl_name: &str) -> (String, String) {
+    // Triangles are grouped by material so each material is only selected once
+    let mut materials = Vec::<(Color, Vec<&Triangle>)>::new();
+    let mut material_indices = HashMap::<[u32; 3], usize>::new();
+    for triangle in &scene.triangles {
+        let color = triangle.color.0;
+        let key = [color.x.to_bits(), color.y.to_bits(), color.z.to_bits()];
+        let index = *material_indices.entry(key).or_insert_with(|| {
+            materials.push((triangle.color, Vec::new()));
+            materials.len() - 1
+        });
+        materials[index].1.push(triangle);
+    }
+
+    let mut obj = format!("mtllib {}\n", mtl_name);
+    let mut mtl = String::new();
+    // OBJ indices start at 1
+    let mut vertex_count = 0;
+    let mut normal_count = 0;
+    for (index, (color, triangles)) in materials.iter().enumerate() {
+        mtl.push_str(&format!(
+            "newmtl color_{}\nKd {} {} {}\n\n",
+            index, color.0.x, color.0.y, color.0.z
+        ));
+
+        obj.push_str(&format!("usemtl color_{}\n", index));
+        for triangle in triangles {
+            for vertex in [triangle.v0, triangle.v1, triangle.v2] {
+                obj.push_str(&format!("v {} {} {}\n", vertex.x, vertex.y, vertex.z));
+            }
+            // OBJ sees the counter clockwise side as the front, Triangle.normal points the other way
+            let normal = (triangle.v1 - triangle.v0)
+                .cross(&(triangle.v2 - triangle.v0))
+                .normalize();
+            obj.push_str(&format!("vn {} {} {}\n", normal.x, normal.y, normal.z));
+            normal_count += 1;
+            obj.push_str(&format!(
+                "f {}//{n} {}//{n} {}//{n}\n",
+                vertex_count + 1,
+                vertex_count + 2,
+                vertex_count + 3,
+                n = normal_count
+            ));
+            vertex_count += 3;
+        }
+    }
+    (obj, mtl)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::to_obj;
+    use crate::{scene::Scene, Color, Triangle};
+
+    #[test]
+    fn materials_per_color() {
+        let triangle = |color: Color| {
+            Triangle::new(
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                color,
+            )
+        };
+        let mut scene = Scene::new();
+        scene.instantiate_model(
+            &[
+                triangle(Color::RED),
+                triangle(Color::new(0.5, 0.25, 1.0)),
+                triangle(Color::RED),
+            ],
+            Vector3::new(0.0, 0.0, 2.0),
+        );
+        scene.instantiate_box(Vector3::zeros(), 1.0, Color::WHITE, 0.5);
+
+        let (obj, mtl) = to_obj(&scene, "map.mtl");
+        assert_eq!(
+            mtl,
+            "newmtl color_0\nKd 1 0 0\n\nnewmtl color_1\nKd 0.5 0.25 1\n\n"
+        );
+        assert_eq!(
+            obj,
+            "mtllib map.mtl
+usemtl color_0
+v 0 0 2
+v 1 0 2
+v 0 1 2
+vn 0 0 1
+f 1//1 2//1 3//1
+v 0 0 2
+v 1 0 2
+v 0 1 2
+vn 0 0 1
+f 4//2 5//2 6//2
+usemtl color_1
+v 0 0 2
+v 1 0 2
+v 0 1 2
+vn 0 0 1
+f 7//3 8//3 9//3
+"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use anyhow::ensure;
+use nalgebra::Vector3;
+
+use crate::{example::ExampleTile, grid::Grid, tile_data::TileData, Direction};
+
+// Turns every pattern_size * pattern_size area of the example into a tile, weighted by how often
+// it occurs. Each tile shows the model in the bottom left corner of its pattern.
+// The edges of a pattern are the part it shares with the pattern next to it, so two patterns
+// can only be placed next to each other when they overlap without differences.
+pub fn extract_patterns(
+    example: &Grid<ExampleTile>,
+    pattern_size: usize,
+) -> anyhow::Result<Vec<TileData>> {
+    ensure!(
+        example.width() >= pattern_size && example.height() >= pattern_size,
+        "The example has to be at least {} tiles wide and high",
+        pattern_size
+    );
+
+    // Sorted so the same example always gives the same tiles
+    let mut patterns = BTreeMap::<Vec<ExampleTile>, u32>::new();
+    for y in 0..=(example.height() - pattern_size) {
+        for x in 0..=(example.width() - pattern_size) {
+            let mut pattern = vec![];
+            for pattern_y in 0..pattern_size {
+                for pattern_x in 0..pattern_size {
+                    pattern.push(
+                        example
+                            .get(Vector3::new(x + pattern_x, y + pattern_y, 0))
+                            .clone(),
+                    );
+                }
+            }
+            *patterns.entry(pattern).or_insert(0) += 1;
+        }
+    }
+
+    Ok(patterns
+        .into_iter()
+        .enumerate()
+        .map(|(index, (pattern, count))| {
+            // Row by row description of the tiles within the given columns and rows
+            let area = |columns: std::ops::Range<usize>, rows: std::ops::Range<usize>| {
+                rows.map(|y| {
+                    columns
+                        .clone()
+                        .map(|x| {
+                            let tile = &pattern[y * pattern_size + x];
+                            format!("{}@{}", tile.model, tile.rotation)
+                        })
+                        .collect::<Vec<String>>()
+                        .join(",")
+                })
+                .collect::<Vec<String>>()
+                .join(";")
+            };
+
+            let mut tile = TileData {
+                model: pattern[0].model.clone(),
+                rotation: pattern[0].rotation,
+                pattern: Some(index),
+                weight: count,
+                ..Default::default()
+            };
+            let (all, without_first, without_last) =
+                (0..pattern_size, 1..pattern_size, 0..pattern_size - 1);
+            tile.set_edge(
+                Direction::Up,
+                area(all.clone(), without_first.clone()),
+                None,
+            );
+            tile.set_edge(Direction::Right, area(without_first, all.clone()), None);
+            tile.set_edge(
+                Direction::Down,
+                area(all.clone(), without_last.clone()),
+                None,
+            );
+            tile.set_edge(Direction::Left, area(without_last, all), None);
+            tile
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_patterns;
+    use crate::{
+        config::WfcConfig, example::parse_example, topology::Topology, wave_function_collapse::WFC,
+    };
+
+    #[test]
+    fn patterns_are_weighted_by_frequency() {
+        let example = parse_example("a, a, a\na, a, b", "test").unwrap();
+        let patterns = extract_patterns(&example, 2).unwrap();
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns.iter().map(|tile| tile.weight).sum::<u32>(), 2);
+        assert!(extract_patterns(&example, 3).is_err());
+    }
+
+    #[test]
+    fn generated_map_follows_example() {
+        // Columns alternate between a and b
+        let example = parse_example("a, b, a, b, a\na, b, a, b, a\na, b, a, b, a", "test").unwrap();
+        let tile_datas = extract_patterns(&example, 2).unwrap();
+
+        let config = WfcConfig::builder()
+            .map_size(12)
+            .max_backtracks(100)
+            .seed(4)
+            .build()
+            .unwrap();
+        let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        let corner_model = &tile_datas[grid.cells()[0].expect("Every position has a tile")].model;
+        for (position, tile) in grid.iter() {
+            let model = &tile_datas[tile.expect("Every position has a tile")].model;
+            assert_eq!(model == corner_model, position.x % 2 == 0);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::ensure;
+use nalgebra::Vector3;
+
+use crate::{
+    tile_data::{TileData, TileId},
+    tileset,
+};
+
+// Tile positions a pin applies to. Rows are counted from the bottom of the map and columns from
+// the left, rows, columns and the border cover every layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinArea {
+    Tile(Vector3<usize>),
+    Row(usize),
+    Column(usize),
+    Border,
+}
+
+// Limits the tile positions in the area to the given tiles before the map is generated,
+// a pin with a single tile places that tile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pin {
+    pub area: PinArea,
+    pub tiles: Vec<TileId>,
+}
+
+impl Pin {
+    // Returns: the tile positions of the area that are inside a map of map_size
+    pub fn positions(&self, map_size: Vector3<usize>) -> Vec<Vector3<usize>> {
+        let mut positions = vec![];
+        for z in 0..map_size.z {
+            for y in 0..map_size.y {
+                for x in 0..map_size.x {
+                    let position = Vector3::new(x, y, z);
+                    let in_area = match self.area {
+                        PinArea::Tile(tile_position) => position == tile_position,
+                        PinArea::Row(row) => y == row,
+                        PinArea::Column(column) => x == column,
+                        PinArea::Border => {
+                            x == 0 || y == 0 || x == map_size.x - 1 || y == map_size.y - 1
+                        }
+                    };
+                    if in_area {
+                        positions.push(position);
+                    }
+                }
+            }
+        }
+        positions
+    }
+}
+
+// Reads a pins file, see parse_pins
+pub fn load_pins(path: &str, tiles: &[TileData]) -> anyhow::Result<Vec<Pin>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|_| anyhow::format_err!("Could not find pins file {}", path))?;
+
+    parse_pins(&text, path, tiles)
+}
+
+// Each line is an area followed by the tiles that can be placed there, written like in rules:
+// tile, x, y, tiles / tile, x, y, z, tiles / row, y, tiles / column, x, tiles / border, tiles
+// Source is the name of where the text comes from, used in error messages
+pub fn parse_pins(text: &str, source: &str, tiles: &[TileData]) -> anyhow::Result<Vec<Pin>> {
+    let mut pins = vec![];
+    for (index, line) in text.lines().enumerate() {
+        // Ignore comments and empty lines
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let values = line
+            .replace(' ', "")
+            .split(',')
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let numbers = values[1..]
+            .iter()
+            .take_while(|value| value.parse::<usize>().is_ok())
+            .map(|value| value.parse::<usize>().unwrap())
+            .collect::<Vec<usize>>();
+        let area = match (values[0].as_str(), numbers.as_slice()) {
+            ("tile", [x, y]) => Some(PinArea::Tile(Vector3::new(*x, *y, 0))),
+            ("tile", [x, y, z]) => Some(PinArea::Tile(Vector3::new(*x, *y, *z))),
+            ("row", [y]) => Some(PinArea::Row(*y)),
+            ("column", [x]) => Some(PinArea::Column(*x)),
+            ("border", []) => Some(PinArea::Border),
+            _ => None,
+        };
+        let names = &values[1 + numbers.len()..];
+        ensure!(
+            area.is_some() && !names.is_empty(),
+            format!(
+                "Error in {} on line {}. Pins are written as tile, x, y, tiles or tile, x, y, z, tiles or row, y, tiles or column, x, tiles or border, tiles",
+                source,
+                index + 1
+            )
+        );
+
+        let mut pin_tiles = vec![];
+        for name in names {
+            let tile_ids = tileset::find_tiles(tiles, name, index + 1)
+                .map_err(|error| anyhow::format_err!("Error in {}. {}", source, error))?;
+            pin_tiles.extend(tile_ids);
+        }
+        pins.push(Pin {
+            area: area.unwrap(),
+            tiles: pin_tiles,
+        });
+    }
+
+    Ok(pins)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::{parse_pins, Pin, PinArea};
+    use crate::test_tiles::named_tile as tile;
+
+    #[test]
+    fn parse_pin_areas() {
+        let tiles = [
+            tile("river.glb", 0),
+            tile("river.glb", 1),
+            tile("house.glb", 0),
+        ];
+        let pins = parse_pins(
+            "# comment\nrow, 3, river.glb\ntile, 2, 7, 1, house.glb@0\nborder, river.glb@1, house.glb",
+            "test",
+            &tiles,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pins,
+            vec![
+                Pin {
+                    area: PinArea::Row(3),
+                    tiles: vec![0, 1]
+                },
+                Pin {
+                    area: PinArea::Tile(Vector3::new(2, 7, 1)),
+                    tiles: vec![2]
+                },
+                Pin {
+                    area: PinArea::Border,
+                    tiles: vec![1, 2]
+                },
+            ]
+        );
+        assert_eq!(pins[0].positions(Vector3::new(5, 5, 2)).len(), 10);
+        assert_eq!(pins[2].positions(Vector3::new(5, 5, 1)).len(), 16);
+        for line in [
+            "row, river.glb",
+            "tile, 1, river.glb",
+            "column, 2",
+            "row, 1, castle.glb",
+        ] {
+            assert!(parse_pins(line, "test", &tiles).is_err());
+        }
+    }
+}
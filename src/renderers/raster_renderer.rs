@@ -32,20 +32,41 @@ impl Renderer for Rasterizer {
     ) -> Result<(), Self::Error> {
         canvas.draw(&mut self.props)?;
 
-        let vertex_data = scene
+        let opaque_triangles = scene
             .triangles
             .iter()
-            .flat_map(|triangle| {
+            .map(|triangle| (triangle, Color::from(triangle.color)));
+        // Translucent triangles come last so they are drawn over the opaque ones
+        let translucent_triangles = scene.translucent_triangles.iter().map(|translucent| {
+            let color = translucent.triangle.color.0;
+            (
+                &translucent.triangle,
+                Color::from_rgba([color.x, color.y, color.z, translucent.alpha]),
+            )
+        });
+        let vertex_data = opaque_triangles
+            .chain(translucent_triangles)
+            .flat_map(|(triangle, color)| {
                 vec![
-                    Vertex::new(triangle.v0.into(), triangle.color.into()),
-                    Vertex::new(triangle.v1.into(), triangle.color.into()),
-                    Vertex::new(triangle.v2.into(), triangle.color.into()),
+                    Vertex::new(triangle.v0.into(), color),
+                    Vertex::new(triangle.v1.into(), color),
+                    Vertex::new(triangle.v2.into(), color),
                 ]
             })
             .collect::<Vec<Vertex>>();
 
-        let mut index_data = (0..vertex_data.len() as u16).collect::<Vec<u16>>();
-        index_data.extend((0..vertex_data.len() as u16).map(|i| vertex_data.len() as u16 - i - 1));
+        // Every triangle is drawn from both sides
+        let double_sided = |vertices: std::ops::Range<usize>| {
+            vertices
+                .clone()
+                .chain(vertices.rev())
+                .map(|vertex| vertex as u32)
+                .collect::<Vec<u32>>()
+        };
+        let opaque_vertex_count = scene.triangles.len() * 3;
+        let mut index_data = double_sided(0..opaque_vertex_count);
+        let index_count = index_data.len();
+        index_data.extend(double_sided(opaque_vertex_count..vertex_data.len()));
 
         let vertex_buf = canvas
             .device
@@ -66,7 +87,8 @@ impl Renderer for Rasterizer {
         self.props.mesh_props = Some(MeshProps {
             vertex_buf,
             index_buf,
-            index_count: index_data.len(),
+            index_count,
+            translucent_index_count: index_data.len() - index_count,
         });
 
         self.props.aspect_ratio =
@@ -226,6 +248,7 @@ pub struct MeshProps {
     pub vertex_buf: wgpu::Buffer,
     pub index_buf: wgpu::Buffer,
     pub index_count: usize,
+    pub translucent_index_count: usize, // Drawn after the first index_count indexes
 }
 
 pub struct WgpuRenderProps {
@@ -233,6 +256,7 @@ pub struct WgpuRenderProps {
     pub bind_group: wgpu::BindGroup,
     pub uniform_buf: wgpu::Buffer,
     pub pipeline: wgpu::RenderPipeline,
+    pub pipeline_translucent: wgpu::RenderPipeline,
     pub pipeline_wire: Option<wgpu::RenderPipeline>,
     pub depth_texture: Texture,
     pub aspect_ratio: f32,
@@ -342,6 +366,41 @@ impl WgpuRenderProps {
             multiview: None,
         });
 
+        // Blends with what is behind it and doesn't hide other translucent triangles
+        let pipeline_translucent = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.view_formats[0],
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
         let pipeline_wire = if device
             .features()
             .contains(wgpu::Features::POLYGON_MODE_LINE)
@@ -394,6 +453,7 @@ impl WgpuRenderProps {
             bind_group,
             uniform_buf,
             pipeline,
+            pipeline_translucent,
             pipeline_wire,
             depth_texture,
         }
@@ -437,9 +497,10 @@ impl WgpuRenderProps {
                 vertex_buf,
                 index_buf,
                 index_count,
+                translucent_index_count,
             }) = self.mesh_props.as_ref()
             {
-                rpass.set_index_buffer(index_buf.slice(..), wgpu::IndexFormat::Uint16);
+                rpass.set_index_buffer(index_buf.slice(..), wgpu::IndexFormat::Uint32);
                 rpass.set_vertex_buffer(0, vertex_buf.slice(..));
                 rpass.pop_debug_group();
                 rpass.insert_debug_marker("Draw!");
@@ -448,6 +509,14 @@ impl WgpuRenderProps {
                     rpass.set_pipeline(pipe);
                     rpass.draw_indexed(0..(*index_count) as u32, 0, 0..1);
                 }
+                if *translucent_index_count > 0 {
+                    rpass.set_pipeline(&self.pipeline_translucent);
+                    rpass.draw_indexed(
+                        *index_count as u32..(index_count + translucent_index_count) as u32,
+                        0,
+                        0..1,
+                    );
+                }
             }
         }
 
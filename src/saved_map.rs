@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fmt, path::Path, str::FromStr};
+
+use anyhow::ensure;
+use nalgebra::Vector3;
+use tracing::warn;
+
+use crate::{
+    config::WfcConfig, grid::Grid, map_output, scene::Scene, tile_data::TileId, tileset::Tileset,
+    wave_function_collapse::LoadedTileset,
+};
+
+// Line between the config and the tiles of a saved map
+const TILES_HEADER: &str = "[tiles]";
+
+// A generated map together with the config it was generated with, so it can be shown again
+// without generating it. Tiles are saved by TileData::unique_name instead of by TileId so the
+// map can still be read after tiles have been added to or removed from the tileset.
+//
+// The file starts with the config in the format of config.txt, followed by a [tiles] line and
+// the tiles in the format of map_output::to_text
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedMap {
+    pub config: WfcConfig, // The seed is the one the map was generated with
+    pub tiles: Grid<Option<String>>,
+}
+
+impl SavedMap {
+    pub fn new(
+        config: &WfcConfig,
+        seed: u64,
+        grid: &Grid<Option<TileId>>,
+        tileset: &Tileset,
+    ) -> Self {
+        Self {
+            config: WfcConfig {
+                seed,
+                ..config.clone()
+            },
+            tiles: map_output::tile_names(grid, tileset),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|_| anyhow::format_err!("Could not find saved map {}", path.display()))?;
+
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string())
+            .map_err(|error| anyhow::format_err!("Could not write {}: {}", path.display(), error))
+    }
+
+    // Source is the name of where the text comes from, used in error messages
+    fn parse(text: &str, source: &str) -> anyhow::Result<Self> {
+        let (config, tiles) = text
+            .split_once(&format!("\n{}\n", TILES_HEADER))
+            .ok_or_else(|| {
+                anyhow::format_err!("{} has no {} line before the tiles", source, TILES_HEADER)
+            })?;
+
+        let config = WfcConfig::parse(config, source)?;
+        let size = Vector3::new(config.map_width, config.map_height, config.map_depth);
+        let tiles = map_output::parse_names(tiles, size, source)?;
+        Ok(Self { config, tiles })
+    }
+
+    // Returns: the tile at every tile position, tiles that are no longer in the tileset are left
+    // empty together with their names
+    pub fn grid(&self, tileset: &Tileset) -> anyhow::Result<(Grid<Option<TileId>>, Vec<String>)> {
+        let mut tile_ids = HashMap::new();
+        for (tile, tile_data) in tileset.tiles.iter().enumerate() {
+            let name = tile_data.unique_name();
+            ensure!(
+                !tile_ids.contains_key(&name),
+                "The tileset has more than one tile called {}, the saved map can't tell them apart",
+                name
+            );
+            tile_ids.insert(name, tile);
+        }
+
+        let mut missing = Vec::new();
+        let cells = self
+            .tiles
+            .cells()
+            .iter()
+            .map(|name| {
+                let name = name.as_ref()?;
+                let tile = tile_ids.get(name).copied();
+                if tile.is_none() && !missing.contains(name) {
+                    missing.push(name.clone());
+                }
+                tile
+            })
+            .collect();
+        Ok((Grid::new(self.tiles.size(), cells), missing))
+    }
+
+    // Reads the tileset of the config and places the models of the tiles in a new scene
+    pub fn scene(&self) -> anyhow::Result<Scene> {
+        let LoadedTileset { tileset, models } = LoadedTileset::load(&self.config)?;
+        let (grid, missing) = self.grid(&tileset)?;
+        if !missing.is_empty() {
+            warn!(
+                "The tileset has no tiles called {}, they are left empty",
+                missing.join(", ")
+            );
+        }
+
+        let mut scene = Scene::new();
+        scene.instantiate_grid(&grid, &models, tileset.topology, Vector3::zeros());
+        Ok(scene)
+    }
+}
+
+impl fmt::Display for SavedMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Map saved with the config it was generated with")?;
+        write!(f, "{}", self.config)?;
+        writeln!(f, "{}", TILES_HEADER)?;
+        write!(f, "{}", map_output::names_to_text(&self.tiles))
+    }
+}
+
+impl FromStr for SavedMap {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        Self::parse(text, "saved map")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::SavedMap;
+    use crate::{
+        config::WfcConfig, example::parse_example, grid::Grid, overlapping_model::extract_patterns,
+        test_tiles::named_tile as tile, tileset::Tileset, topology::Topology,
+    };
+
+    #[test]
+    fn saved_map_survives_tileset_changes() {
+        let tileset = Tileset::new(
+            vec![
+                tile("grass.glb", 0),
+                tile("road.glb", 0),
+                tile("road.glb", 1),
+            ],
+            Topology::Square,
+        );
+        let config = WfcConfig::builder()
+            .tileset_path("./assets/city")
+            .map_width(2)
+            .map_height(2)
+            .build()
+            .unwrap();
+        let grid = Grid::new(Vector3::new(2, 2, 1), vec![Some(0), Some(2), None, Some(1)]);
+
+        let saved = SavedMap::new(&config, 9, &grid, &tileset);
+        let text = saved.to_string();
+        assert!(text.contains("seed=9\n"));
+        assert!(text.ends_with("[tiles]\n?, road.glb@0\ngrass.glb@0, road.glb@1\n"));
+        let loaded = text.parse::<SavedMap>().unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.grid(&tileset).unwrap(), (grid, vec![]));
+
+        // Removing a tile changes the ids of the tiles after it
+        let edited_tileset = Tileset::new(
+            vec![tile("road.glb", 1), tile("grass.glb", 0)],
+            Topology::Square,
+        );
+        assert_eq!(
+            loaded.grid(&edited_tileset).unwrap(),
+            (
+                Grid::new(Vector3::new(2, 2, 1), vec![Some(1), Some(0), None, None]),
+                vec!["road.glb@0".to_owned()]
+            )
+        );
+
+        assert!("tile_set=a\nmap_size=2\n?, ?\n?, ?\n"
+            .parse::<SavedMap>()
+            .is_err());
+    }
+
+    #[test]
+    fn saved_map_tells_patterns_apart() {
+        // Both patterns show a@0 in their bottom left corner
+        let example = parse_example("a, a, a\na, a, b", "test").unwrap();
+        let tileset = Tileset::new(extract_patterns(&example, 2).unwrap(), Topology::Square);
+        let config = WfcConfig::builder()
+            .tileset_path("./assets/city")
+            .map_width(2)
+            .map_height(1)
+            .build()
+            .unwrap();
+        let grid = Grid::new(Vector3::new(2, 1, 1), vec![Some(1), Some(0)]);
+
+        let saved = SavedMap::new(&config, 3, &grid, &tileset);
+        assert!(saved.to_string().ends_with("[tiles]\na@0#1, a@0#0\n"));
+        let loaded = saved.to_string().parse::<SavedMap>().unwrap();
+        assert_eq!(loaded.grid(&tileset).unwrap(), (grid, vec![]));
+
+        let duplicated = Tileset::new(vec![tile("a", 0), tile("a", 0)], Topology::Square);
+        assert!(loaded.grid(&duplicated).is_err());
+    }
+}
@@ -1,9 +1,28 @@
+use std::ops::Range;
+
 use nalgebra::Vector3;
 
-use crate::Triangle;
+use crate::{grid::Grid, tile_data::TileId, topology::Topology, Color, Triangle};
+
+// Triangle that can be seen through, an alpha of 0 is invisible and 1 is opaque
+#[derive(Debug, Clone, Copy)]
+pub struct TranslucentTriangle {
+    pub triangle: Triangle,
+    pub alpha: f32,
+}
+
+// Model of a tile placed by instantiate_grid, kept so the tile can be exported on its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileInstance {
+    pub tile: TileId,
+    pub position: Vector3<f32>,
+    pub triangles: Range<usize>, // The moved copy of the model in Scene.triangles
+}
 
 pub struct Scene {
     pub triangles: Vec<Triangle>,
+    pub tiles: Vec<TileInstance>,
+    pub translucent_triangles: Vec<TranslucentTriangle>, // Drawn after the other triangles
     pub light_pos: Vector3<f32>,
     pub light_color: Vector3<f32>,
     pub indirect_light: Vector3<f32>,
@@ -25,12 +44,74 @@ impl Scene {
             .collect::<Vec<Triangle>>();
         self.triangles.append(&mut instantiated_model);
     }
+
+    // Places the model of every tile in a generated map, models are indexed by TileId
+    // Offset moves the whole map, for example to where a chunk of a larger world is
+    pub fn instantiate_grid(
+        &mut self,
+        grid: &Grid<Option<TileId>>,
+        models: &[Vec<Triangle>],
+        topology: Topology,
+        offset: Vector3<f32>,
+    ) {
+        for (position, tile) in grid.iter() {
+            if let Some(tile) = tile {
+                let position = topology.world_position(position) + offset;
+                let start = self.triangles.len();
+                self.instantiate_model(&models[*tile], position);
+                self.tiles.push(TileInstance {
+                    tile: *tile,
+                    position,
+                    triangles: start..self.triangles.len(),
+                });
+            }
+        }
+    }
+
+    // Adds a see-through box with sides of size around center
+    pub fn instantiate_box(&mut self, center: Vector3<f32>, size: f32, color: Color, alpha: f32) {
+        let corner = |x: f32, y: f32, z: f32| center + Vector3::new(x, y, z) * size / 2.0;
+        let corners = [
+            corner(-1.0, -1.0, -1.0),
+            corner(1.0, -1.0, -1.0),
+            corner(1.0, 1.0, -1.0),
+            corner(-1.0, 1.0, -1.0),
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+        ];
+        // Two triangles for each side, indexes into corners
+        let sides = [
+            [0, 2, 1, 0, 3, 2],
+            [4, 5, 6, 4, 6, 7],
+            [0, 1, 5, 0, 5, 4],
+            [1, 2, 6, 1, 6, 5],
+            [2, 3, 7, 2, 7, 6],
+            [3, 0, 4, 3, 4, 7],
+        ];
+        for side in sides {
+            for triangle in side.chunks(3) {
+                self.translucent_triangles.push(TranslucentTriangle {
+                    triangle: Triangle::new(
+                        corners[triangle[0]],
+                        corners[triangle[1]],
+                        corners[triangle[2]],
+                        color,
+                    ),
+                    alpha,
+                });
+            }
+        }
+    }
 }
 
 impl Default for Scene {
     fn default() -> Self {
         Self {
             triangles: vec![],
+            tiles: vec![],
+            translucent_triangles: vec![],
             light_pos: Vector3::new(-0.5, 0.0, 0.7),
             light_color: Vector3::new(14.0, 14.0, 14.0),
             indirect_light: Vector3::new(0.5, 0.5, 0.5),
@@ -0,0 +1,47 @@
+// Tiles shared by the tests of several modules
+use crate::{adjacency::AdjacencyRules, tile_data::TileData, topology::Topology};
+
+// Square tile with the up, right, down and left edges
+pub fn tile(weight: u32, edges: [&str; 4]) -> TileData {
+    TileData {
+        weight,
+        up_edge: edges[0].to_owned(),
+        right_edge: edges[1].to_owned(),
+        down_edge: edges[2].to_owned(),
+        left_edge: edges[3].to_owned(),
+        ..Default::default()
+    }
+}
+
+// Tile that is only told apart by its name, model@rotation
+pub fn named_tile(model: &str, rotation: usize) -> TileData {
+    TileData {
+        model: model.to_owned(),
+        rotation,
+        ..Default::default()
+    }
+}
+
+// Same rules as assets/test, with all rotations written out
+pub fn pond_tiles() -> Vec<TileData> {
+    vec![
+        tile(16, ["grass", "grass", "grass", "grass"]),
+        tile(1, ["pond", "grass", "grass", "grass"]),
+        tile(1, ["grass", "pond", "grass", "grass"]),
+        tile(1, ["grass", "grass", "pond", "grass"]),
+        tile(1, ["grass", "grass", "grass", "pond"]),
+        tile(1, ["pond", "grass", "pond", "grass"]),
+        tile(1, ["grass", "pond", "grass", "pond"]),
+    ]
+}
+
+// Three colors where neighbours have different colors, which can run into contradictions
+pub fn three_colors() -> (Vec<TileData>, AdjacencyRules) {
+    let mut rules = AdjacencyRules::new(3);
+    for (color, other_color) in [(0, 1), (1, 2), (2, 0)] {
+        for direction in Topology::Square.iterator() {
+            rules.allow(color, direction, other_color);
+        }
+    }
+    (vec![tile(1, ["", "", "", ""]); 3], rules)
+}
@@ -1,19 +1,24 @@
-use nalgebra::{Vector2, Vector3};
+use nalgebra::Vector3;
 use rand::{
     distributions::{Distribution, WeightedIndex},
     rngs::StdRng,
 };
 
-use crate::{scene::Scene, tile_data::TileData, Direction};
+use crate::{
+    adjacency::AdjacencyRules,
+    tile_data::{TileData, TileId},
+    Direction,
+};
 
-pub struct Tile<'a> {
-    pub data: Option<&'a TileData>,
-    pub possible_tiles: Vec<&'a TileData>,
-    pub tile_position: Vector2<usize>, // Position in grid
+#[derive(Clone)]
+pub struct Tile {
+    pub data: Option<TileId>,
+    pub possible_tiles: Vec<TileId>,
+    pub tile_position: Vector3<usize>, // Position in grid
 }
 
-impl<'a> Tile<'a> {
-    pub fn new(possible_tiles: Vec<&'a TileData>, position: Vector2<usize>) -> Self {
+impl Tile {
+    pub fn new(possible_tiles: Vec<TileId>, position: Vector3<usize>) -> Self {
         Self {
             data: None,
             possible_tiles,
@@ -21,51 +26,92 @@ impl<'a> Tile<'a> {
         }
     }
 
-    pub fn collapse(&mut self, scene: &mut Scene, random: &mut StdRng) -> bool {
+    // Map size is used to look up the weight of each option at the position of this tile.
+    // Bias multiplies the weight of each tile, indexed by TileId
+    pub fn collapse(
+        &mut self,
+        tile_datas: &[TileData],
+        map_size: Vector3<usize>,
+        bias: &[f32],
+        random: &mut StdRng,
+    ) -> bool {
         // Already collapsed
         if self.data.is_some() {
             return false;
         }
 
-        let weights: Vec<u32> = self.possible_tiles.iter().map(|t| t.weight).collect();
         // Impossible to collapse
-        if weights.is_empty() {
+        if self.possible_tiles.is_empty() {
             return false;
         }
 
-        let choosen_tile = WeightedIndex::new(weights).unwrap().sample(random);
+        // The weight maps and bias can give every option no weight here, then the bias and
+        // at last the plain weights are used
+        let weights = |weight: &dyn Fn(TileId) -> f32| {
+            WeightedIndex::new(self.possible_tiles.iter().map(|t| weight(*t))).ok()
+        };
+        let weighted_index =
+            weights(&|t| tile_datas[t].weight_at(self.tile_position, map_size) * bias[t])
+                .or_else(|| weights(&|t| tile_datas[t].weight as f32 * bias[t]))
+                .or_else(|| weights(&|t| tile_datas[t].weight as f32))
+                .expect("Tiles have a positive weight");
+        let choosen_tile = weighted_index.sample(random);
 
         // Place tile
         self.data = Some(self.possible_tiles[choosen_tile]);
         self.possible_tiles = vec![];
-        scene.instantiate_model(
-            &self.data.unwrap().model,
-            Vector3::<f32>::new(
-                self.tile_position.x as f32,
-                self.tile_position.y as f32,
-                0.0,
-            ),
-        );
 
         true
     }
 
-    // Remove options after neighbouring tile has changed
-    // Direction is the the direction of this tiles edge that should be checked
-    // Edge is the new edge type of that edge
-    // Returns: impossible to collapse
-    pub fn remove_options(
+    // Remove options that can't be placed next to any of the options of a neighbouring tile
+    // Direction is the the direction of this tiles edge that faces the neighbour
+    // Returns: the removed options
+    pub fn remove_unsupported_options(
         &mut self,
+        rules: &AdjacencyRules,
         direction: Direction,
-        edge: &str,
-        suffix: Option<&str>,
-    ) -> bool {
-        self.possible_tiles = self
-            .possible_tiles
-            .iter()
-            .copied()
-            .filter(|tile| tile.check_edge(direction, edge, suffix))
-            .collect::<Vec<&'a TileData>>();
+        neighbour_options: &[TileId],
+    ) -> Vec<TileId> {
+        self.remove_options_where(|tile| {
+            !neighbour_options
+                .iter()
+                .any(|neighbour| rules.is_allowed(*neighbour, direction.get_opposite(), tile))
+        })
+    }
+
+    // Returns: the removed options
+    pub fn remove_options_where(&mut self, remove: impl Fn(TileId) -> bool) -> Vec<TileId> {
+        let mut removed = vec![];
+        self.possible_tiles.retain(|tile| {
+            let keep = !remove(*tile);
+            if !keep {
+                removed.push(*tile);
+            }
+            keep
+        });
+        removed
+    }
+
+    // Puts back options that were removed, see remove_options_where
+    pub fn restore_options(&mut self, options: &[TileId]) {
+        self.possible_tiles.extend(options);
+        // Options are kept in order of TileId, the order decides which tile a random number picks
+        self.possible_tiles.sort_unstable();
+    }
+
+    // The tiles this tile can still become
+    pub fn options(&self) -> Vec<TileId> {
+        match self.data {
+            Some(data) => vec![data],
+            None => self.possible_tiles.clone(),
+        }
+    }
+
+    // Remove a single option that is known to lead to a contradiction
+    // Returns: impossible to collapse
+    pub fn ban(&mut self, tile_id: TileId) -> bool {
+        self.possible_tiles.retain(|tile| *tile != tile_id);
         self.possible_tiles.is_empty()
     }
 }
@@ -0,0 +1,166 @@
+use anyhow::ensure;
+
+use crate::tile_data::TileId;
+
+// Number of tiles, either fixed or relative to the number of tile positions in the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Amount {
+    Tiles(usize),
+    Percent(f32),
+}
+
+impl Amount {
+    // Reads a whole number or a percentage like 10%
+    pub fn parse(value: &str, line: usize) -> anyhow::Result<Self> {
+        let amount = match value.strip_suffix('%') {
+            Some(percent) => percent
+                .parse::<f32>()
+                .ok()
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .map(Amount::Percent),
+            None => value.parse::<usize>().ok().map(Amount::Tiles),
+        };
+        ensure!(
+            amount.is_some(),
+            format!(
+                "On line {} the amount can only be a non negative integer or a percentage between 0% and 100%",
+                line
+            )
+        );
+        Ok(amount.unwrap())
+    }
+
+    // Percentages of the map are rounded up for minimums and down for maximums
+    fn tiles(&self, cell_count: usize, round_up: bool) -> usize {
+        match self {
+            Amount::Tiles(tiles) => *tiles,
+            Amount::Percent(percent) => {
+                let tiles = percent / 100.0 * cell_count as f32;
+                if round_up {
+                    tiles.ceil() as usize
+                } else {
+                    tiles.floor() as usize
+                }
+            }
+        }
+    }
+}
+
+// Limits how many times a group of tiles, like all rotations of a model, are placed in the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountLimit {
+    pub tiles: Vec<TileId>,
+    pub min: Option<Amount>,
+    pub max: Option<Amount>,
+}
+
+impl CountLimit {
+    // Returns: how many of the tiles have to be placed in a map with cell_count tile positions
+    pub fn min_tiles(&self, cell_count: usize) -> usize {
+        self.min
+            .map_or(0, |min| min.tiles(cell_count, true).min(cell_count))
+    }
+
+    // Returns: how many of the tiles can be placed in a map with cell_count tile positions
+    pub fn max_tiles(&self, cell_count: usize) -> usize {
+        self.max
+            .map_or(cell_count, |max| max.tiles(cell_count, false))
+    }
+}
+
+// Returns: the weight multiplier of every tile that makes it likely enough that the minimums
+// are reached. Options is what a tile position can become and remaining is how many tile
+// positions are left to collapse, including this one. When the minimum needs every remaining
+// tile position the other tiles get no weight.
+pub fn min_count_bias(
+    limits: &[CountLimit],
+    placed: &[usize],
+    options: &[TileId],
+    weights: &[f32],
+    remaining: usize,
+    cell_count: usize,
+) -> Vec<f32> {
+    let mut bias = vec![1.0; weights.len()];
+    for (limit, placed) in limits.iter().zip(placed) {
+        let missing = limit.min_tiles(cell_count).saturating_sub(*placed);
+        if missing == 0 {
+            continue;
+        }
+
+        // Share of the weight the limited tiles have now and the share they need
+        let weight = |limited: bool| {
+            options
+                .iter()
+                .filter(|option| limit.tiles.contains(option) == limited)
+                .map(|option| weights[*option] * bias[*option])
+                .sum::<f32>()
+        };
+        let (limited_weight, other_weight) = (weight(true), weight(false));
+        let needed_share = missing as f32 / remaining.max(1) as f32;
+        if limited_weight == 0.0 {
+            continue;
+        }
+        if needed_share >= 1.0 {
+            for option in options
+                .iter()
+                .filter(|option| !limit.tiles.contains(option))
+            {
+                bias[*option] = 0.0;
+            }
+            continue;
+        }
+        let share = limited_weight / (limited_weight + other_weight);
+        if share < needed_share {
+            let factor = needed_share * (1.0 - share) / (share * (1.0 - needed_share));
+            for option in options.iter().filter(|option| limit.tiles.contains(option)) {
+                bias[*option] *= factor;
+            }
+        }
+    }
+    bias
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{min_count_bias, Amount, CountLimit};
+
+    #[test]
+    fn amounts_of_map() {
+        let limit = CountLimit {
+            tiles: vec![0],
+            min: Some(Amount::parse("10%", 1).unwrap()),
+            max: Some(Amount::parse("15%", 1).unwrap()),
+        };
+
+        assert_eq!(limit.min_tiles(25), 3);
+        assert_eq!(limit.max_tiles(25), 3);
+        assert_eq!(CountLimit { max: None, ..limit }.max_tiles(25), 25);
+        assert!(Amount::parse("101%", 1).is_err());
+        assert!(Amount::parse("-1", 1).is_err());
+    }
+
+    #[test]
+    fn bias_reaches_needed_share() {
+        let limit = CountLimit {
+            tiles: vec![1],
+            min: Some(Amount::Tiles(5)),
+            max: None,
+        };
+        let weights = [3.0, 1.0, 1.0];
+        let bias = min_count_bias(
+            std::slice::from_ref(&limit),
+            &[0],
+            &[0, 1, 2],
+            &weights,
+            10,
+            100,
+        );
+
+        // Half of the remaining tile positions need the limited tile
+        assert_eq!(bias[1] * weights[1], 4.0);
+        assert_eq!((bias[0], bias[2]), (1.0, 1.0));
+
+        let bias = min_count_bias(&[limit], &[1], &[0, 1], &weights, 4, 100);
+        assert_eq!(bias[0], 0.0);
+    }
+}
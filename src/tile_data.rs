@@ -1,44 +1,187 @@
-use crate::{Direction, Triangle};
+use std::collections::{BTreeMap, BTreeSet};
 
-#[derive(Debug)]
+use nalgebra::Vector3;
+
+use crate::{weight_map::WeightMap, Direction};
+
+// Index of a tile in its tileset
+pub type TileId = usize;
+
+#[derive(Debug, Clone, Default)]
 pub struct TileData {
-    pub model: Vec<Triangle>,
+    pub model: String,          // Model file relative to the tileset folder
+    pub rotation: usize, // Number of rotation steps applied to the model, see Topology::rotation_step
+    pub mirrored: bool,  // Mirrored left to right after rotating
+    pub pattern: Option<usize>, // Pattern of the overlapping model, patterns can share a model
     pub weight: u32,
+    pub weight_map: WeightMap, // Changes the weight depending on where the tile is placed
     pub up_edge: String,
+    pub up_right_edge: String,
     pub right_edge: String,
+    pub down_right_edge: String,
     pub down_edge: String,
+    pub down_left_edge: String,
     pub left_edge: String,
+    pub up_left_edge: String,
+    pub top_edge: String,
+    pub bottom_edge: String,
     pub up_edge_suffix: Option<String>,
+    pub up_right_edge_suffix: Option<String>,
     pub right_edge_suffix: Option<String>,
+    pub down_right_edge_suffix: Option<String>,
     pub down_edge_suffix: Option<String>,
+    pub down_left_edge_suffix: Option<String>,
     pub left_edge_suffix: Option<String>,
+    pub up_left_edge_suffix: Option<String>,
+    pub top_edge_suffix: Option<String>,
+    pub bottom_edge_suffix: Option<String>,
 }
 
 impl TileData {
+    // Returns: the weight of the tile when it is placed at position in a map of map_size
+    pub fn weight_at(&self, position: Vector3<usize>, map_size: Vector3<usize>) -> f32 {
+        self.weight as f32 * self.weight_map.factor(position, map_size)
+    }
+
+    // Name used for the tile in rules, model@rotation with an m after mirrored tiles
+    pub fn name(&self) -> String {
+        format!(
+            "{}@{}{}",
+            self.model,
+            self.rotation,
+            if self.mirrored { "m" } else { "" }
+        )
+    }
+
+    // Name that is different for every tile of a tileset, used to save maps. The name followed by
+    // # and the pattern for tiles of the overlapping model
+    pub fn unique_name(&self) -> String {
+        match self.pattern {
+            Some(pattern) => format!("{}#{}", self.name(), pattern),
+            None => self.name(),
+        }
+    }
+
     pub fn get_edge(&self, direction: Direction) -> &str {
         match direction {
             Direction::Up => self.up_edge.as_str(),
+            Direction::UpRight => self.up_right_edge.as_str(),
             Direction::Right => self.right_edge.as_str(),
+            Direction::DownRight => self.down_right_edge.as_str(),
             Direction::Down => self.down_edge.as_str(),
+            Direction::DownLeft => self.down_left_edge.as_str(),
             Direction::Left => self.left_edge.as_str(),
+            Direction::UpLeft => self.up_left_edge.as_str(),
+            Direction::Top => self.top_edge.as_str(),
+            Direction::Bottom => self.bottom_edge.as_str(),
         }
     }
 
     pub fn get_suffix(&self, direction: Direction) -> Option<&str> {
         match direction {
             Direction::Up => self.up_edge_suffix.as_deref(),
+            Direction::UpRight => self.up_right_edge_suffix.as_deref(),
             Direction::Right => self.right_edge_suffix.as_deref(),
+            Direction::DownRight => self.down_right_edge_suffix.as_deref(),
             Direction::Down => self.down_edge_suffix.as_deref(),
+            Direction::DownLeft => self.down_left_edge_suffix.as_deref(),
             Direction::Left => self.left_edge_suffix.as_deref(),
+            Direction::UpLeft => self.up_left_edge_suffix.as_deref(),
+            Direction::Top => self.top_edge_suffix.as_deref(),
+            Direction::Bottom => self.bottom_edge_suffix.as_deref(),
         }
     }
 
-    pub fn check_edge(&self, direction: Direction, edge: &str, suffix: Option<&str>) -> bool {
+    pub fn set_edge(&mut self, direction: Direction, edge: String, suffix: Option<String>) {
+        let (own_edge, own_suffix) = match direction {
+            Direction::Up => (&mut self.up_edge, &mut self.up_edge_suffix),
+            Direction::UpRight => (&mut self.up_right_edge, &mut self.up_right_edge_suffix),
+            Direction::Right => (&mut self.right_edge, &mut self.right_edge_suffix),
+            Direction::DownRight => (&mut self.down_right_edge, &mut self.down_right_edge_suffix),
+            Direction::Down => (&mut self.down_edge, &mut self.down_edge_suffix),
+            Direction::DownLeft => (&mut self.down_left_edge, &mut self.down_left_edge_suffix),
+            Direction::Left => (&mut self.left_edge, &mut self.left_edge_suffix),
+            Direction::UpLeft => (&mut self.up_left_edge, &mut self.up_left_edge_suffix),
+            Direction::Top => (&mut self.top_edge, &mut self.top_edge_suffix),
+            Direction::Bottom => (&mut self.bottom_edge, &mut self.bottom_edge_suffix),
+        };
+        *own_edge = edge;
+        *own_suffix = suffix;
+    }
+
+    pub fn check_edge(
+        &self,
+        direction: Direction,
+        edge: &str,
+        suffix: Option<&str>,
+        sockets: &Sockets,
+    ) -> bool {
         let own_suffix = self.get_suffix(direction);
         if let (Some(suffix), Some(own_suffix)) = (suffix, own_suffix) {
-            self.get_edge(direction) == edge && suffix != own_suffix // Prevent matching tile with itself rotated
+            // Prevent matching tile with itself rotated
+            sockets.connects(self.get_edge(direction), edge) && suffix != own_suffix
         } else {
-            self.get_edge(direction) == edge
+            sockets.connects(self.get_edge(direction), edge)
+        }
+    }
+}
+
+// Ending of an edge that is the flipped version of an asymmetric edge
+pub const FLIPPED_MARKER: char = '!';
+
+// Which edges connect to each other. Edges connect to themselves unless they have been
+// connected to other edges, like road_in that only connects to road_out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sockets {
+    connections: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Sockets {
+    // Edges are read clockwise around the tile, so the neighbour sees an asymmetric edge flipped.
+    // Every edge that has a flipped version in the tiles only connects to that version.
+    // Rotating a tile keeps the reading order, so rotations don't change which edges connect
+    pub fn from_tiles(tile_datas: &[TileData]) -> Self {
+        let mut sockets = Self::default();
+        for tile_data in tile_datas {
+            for direction in Direction::iterator() {
+                let edge = tile_data.get_edge(direction);
+                if let Some(unflipped_edge) = edge.strip_suffix(FLIPPED_MARKER) {
+                    sockets.connect(unflipped_edge, edge);
+                }
+            }
+        }
+        sockets
+    }
+
+    // Lets the edges connect to each other, they no longer connect to themselves
+    pub fn connect(&mut self, edge: &str, other_edge: &str) {
+        self.connections
+            .entry(edge.to_owned())
+            .or_default()
+            .insert(other_edge.to_owned());
+        self.connections
+            .entry(other_edge.to_owned())
+            .or_default()
+            .insert(edge.to_owned());
+    }
+
+    // Returns: the edge seen in a mirror, the flipped version if the edge has one
+    pub fn mirror_edge(&self, edge: &str) -> String {
+        if let Some(unflipped_edge) = edge.strip_suffix(FLIPPED_MARKER) {
+            return unflipped_edge.to_owned();
+        }
+        let flipped_edge = format!("{}{}", edge, FLIPPED_MARKER);
+        if self.connects(edge, &flipped_edge) {
+            flipped_edge
+        } else {
+            edge.to_owned()
+        }
+    }
+
+    pub fn connects(&self, edge: &str, other_edge: &str) -> bool {
+        match self.connections.get(edge) {
+            Some(connections) => connections.contains(other_edge),
+            None => edge == other_edge,
         }
     }
 }
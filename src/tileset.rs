@@ -0,0 +1,685 @@
+use std::collections::HashMap;
+
+use anyhow::ensure;
+use nalgebra::Rotation3;
+
+use crate::{
+    adjacency::{self, AdjacencyRules},
+    example::{self, ExampleModel},
+    model_loader::ModelLoader,
+    overlapping_model,
+    tile_count::{Amount, CountLimit},
+    tile_data::{Sockets, TileData, TileId},
+    topology::Topology,
+    weight_map::{self, WeightMap},
+    Direction, Triangle,
+};
+
+// Model name for tiles that shouldn't show anything
+pub const EMPTY_MODEL: &str = "none";
+
+#[derive(Clone)]
+pub struct Tileset {
+    pub path: String,
+    pub topology: Topology,
+    pub tiles: Vec<TileData>, // Indexed by TileId
+    pub rules: AdjacencyRules,
+    pub sockets: Sockets, // Which edges connect, also to the border edge of fixed boundaries
+    pub count_limits: Vec<CountLimit>,
+}
+
+impl Tileset {
+    // Creates a tileset that isn't backed by a folder, mostly useful for testing the solver
+    pub fn new(tiles: Vec<TileData>, topology: Topology) -> Self {
+        let sockets = Sockets::from_tiles(&tiles);
+        Self {
+            path: "".to_owned(),
+            topology,
+            rules: AdjacencyRules::from_edges(&tiles, &sockets),
+            sockets,
+            tiles,
+            count_limits: vec![],
+        }
+    }
+
+    // Reads the tile descriptions from tiles_config.txt in the tileset folder
+    pub fn load(tileset_path: &str, topology: Topology) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(format!("{}/tiles_config.txt", tileset_path)).map_err(
+            |_| {
+                anyhow::format_err!(
+                    "Could not find config file {}/tiles_config.txt",
+                    tileset_path
+                )
+            },
+        )?;
+
+        Self::parse(&text, tileset_path, topology)
+    }
+
+    // Every tile declares one edge for each direction of the topology. Lines starting with connect
+    // are socket pairs, see Sockets. Lines starting with allow or deny are rules that change which
+    // tiles fit next to each other, see TileRule. Lines starting with min or max limit how many
+    // times a tile is placed, see CountLimit
+    fn parse(text: &str, tileset_path: &str, topology: Topology) -> anyhow::Result<Self> {
+        let mut tiles: Vec<TileData> = vec![];
+        let mut tile_rules: Vec<TileRule> = vec![];
+        let mut socket_pairs: Vec<(String, String)> = vec![];
+        let mut weight_maps: Vec<(String, WeightMap, usize)> = vec![];
+        let mut count_lines: Vec<(String, bool, Amount, usize)> = vec![];
+        let directions = topology.directions();
+        // A tile can be rotated so that it repeats itself after a full turn
+        let rotation_counts = (1..=directions.len())
+            .filter(|count| directions.len().is_multiple_of(*count))
+            .map(|count| count.to_string())
+            .collect::<Vec<String>>();
+        let symmetries = match topology {
+            Topology::Square => ", or one of the symmetries X, I, \\, T, L or F",
+            Topology::Hex => "",
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            // Ignore comments
+            if line.starts_with('#') {
+                continue;
+            }
+
+            // Validate inputs
+            let values = line
+                .replace(' ', "")
+                .split(',')
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+            if values[0] == "connect" {
+                ensure!(
+                    values.len() == 3,
+                    format!(
+                        "On line {} the socket pair has to be written as connect, edge, edge",
+                        index + 1
+                    )
+                );
+                socket_pairs.push((values[1].clone(), values[2].clone()));
+                continue;
+            }
+            if values[0] == "allow" || values[0] == "deny" {
+                tile_rules.push(TileRule::parse(&values, topology, index + 1)?);
+                continue;
+            }
+            if values[0] == "min" || values[0] == "max" {
+                ensure!(
+                    values.len() == 3,
+                    format!(
+                        "On line {} the limit has to be written as {}, tile, amount",
+                        index + 1,
+                        values[0]
+                    )
+                );
+                let amount = Amount::parse(&values[2], index + 1)?;
+                count_lines.push((values[1].clone(), values[0] == "min", amount, index + 1));
+                continue;
+            }
+            if values[0] == "weights" {
+                let weight_map = parse_weight_map(&values, tileset_path, index + 1)?;
+                weight_maps.push((values[1].clone(), weight_map, index + 1));
+                continue;
+            }
+
+            // The top and bottom edges are optional and only used by maps with several layers
+            ensure!(
+                values.len() == directions.len() + 3 || values.len() == directions.len() + 5,
+                format!(
+                    "Line {} \"{}\" does not contain all values required",
+                    index + 1,
+                    line
+                )
+            );
+            let weight = values[1].parse::<u32>();
+            ensure!(
+                weight.is_ok() && weight.clone().unwrap() > 0,
+                format!(
+                    "On line {} the weight value can only be a positive integer",
+                    index + 1
+                )
+            );
+            let weight = weight.unwrap();
+            let variants = parse_variants(&values[values.len() - 1], topology);
+            ensure!(
+                variants.is_some(),
+                format!(
+                    "On line {} the rotatable value can only be {} or {}, optionally followed by m{}",
+                    index + 1,
+                    rotation_counts[..rotation_counts.len() - 1].join(", "),
+                    rotation_counts[rotation_counts.len() - 1],
+                    symmetries
+                )
+            );
+            let variants = variants.unwrap();
+
+            let edges = values[2..2 + directions.len()]
+                .iter()
+                .zip(directions)
+                .map(|(value, direction)| parse_edge(value, *direction, index + 1))
+                .collect::<anyhow::Result<Vec<(String, Option<String>)>>>()?;
+            // Rotations are around the vertical axis so they don't change the top and bottom edges
+            let (top, bottom) = if values.len() == directions.len() + 5 {
+                (
+                    parse_edge(&values[values.len() - 3], Direction::Top, index + 1)?,
+                    parse_edge(&values[values.len() - 2], Direction::Bottom, index + 1)?,
+                )
+            } else {
+                (("".to_owned(), None), ("".to_owned(), None))
+            };
+
+            // Store each rotation and mirroring as seperate tile
+            for (rotation, mirrored) in variants {
+                let mut tile = TileData {
+                    model: values[0].to_owned(),
+                    rotation,
+                    mirrored,
+                    weight,
+                    ..Default::default()
+                };
+                for (edge_index, direction) in directions.iter().enumerate() {
+                    // Mirroring left to right swaps the edges on the sides
+                    let rotated_index = if mirrored {
+                        directions.len() - edge_index
+                    } else {
+                        edge_index
+                    };
+                    let (edge, suffix) =
+                        edges[(rotated_index + rotation) % directions.len()].clone();
+                    tile.set_edge(*direction, edge, suffix);
+                }
+                tile.set_edge(Direction::Top, top.0.clone(), top.1.clone());
+                tile.set_edge(Direction::Bottom, bottom.0.clone(), bottom.1.clone());
+                tiles.push(tile);
+            }
+        }
+
+        // Mirroring also reverses the order the edges are read in, which flips asymmetric edges
+        let unmirrored_sockets = Sockets::from_tiles(&tiles);
+        for tile in tiles.iter_mut().filter(|tile| tile.mirrored) {
+            for direction in directions {
+                let edge = unmirrored_sockets.mirror_edge(tile.get_edge(*direction));
+                let suffix = tile.get_suffix(*direction).map(|suffix| suffix.to_owned());
+                tile.set_edge(*direction, edge, suffix);
+            }
+        }
+
+        for (name, weight_map, line) in weight_maps {
+            for tile_id in find_tiles(&tiles, &name, line)? {
+                tiles[tile_id].weight_map = weight_map.clone();
+            }
+        }
+
+        let count_limits = count_lines
+            .into_iter()
+            .map(|(name, is_min, amount, line)| {
+                Ok(CountLimit {
+                    tiles: find_tiles(&tiles, &name, line)?,
+                    min: is_min.then_some(amount),
+                    max: (!is_min).then_some(amount),
+                })
+            })
+            .collect::<anyhow::Result<Vec<CountLimit>>>()?;
+
+        let mut sockets = Sockets::from_tiles(&tiles);
+        for (edge, other_edge) in socket_pairs {
+            sockets.connect(&edge, &other_edge);
+        }
+
+        // Denying wins over allowing, no matter the order of the rules
+        let mut rules = AdjacencyRules::from_edges(&tiles, &sockets);
+        tile_rules.sort_by_key(|tile_rule| !tile_rule.allow);
+        for tile_rule in tile_rules {
+            tile_rule.apply(&tiles, &mut rules)?;
+        }
+
+        Ok(Self {
+            path: tileset_path.to_owned(),
+            topology,
+            tiles,
+            rules,
+            sockets,
+            count_limits,
+        })
+    }
+
+    // Learns the tiles and which tiles fit together from an example map instead of from
+    // tiles_config.txt. The models are still loaded from the tileset folder
+    pub fn from_example(
+        tileset_path: &str,
+        example_path: &str,
+        example_model: ExampleModel,
+        pattern_size: usize,
+    ) -> anyhow::Result<Self> {
+        let example = example::load_example(example_path)?;
+        let (tiles, rules) = match example_model {
+            ExampleModel::Overlapping => {
+                let tiles = overlapping_model::extract_patterns(&example, pattern_size)?;
+                let rules = AdjacencyRules::from_edges(&tiles, &Sockets::default());
+                (tiles, rules)
+            }
+            ExampleModel::Tiled => adjacency::learn_tiles(&example),
+        };
+
+        Ok(Self {
+            path: tileset_path.to_owned(),
+            topology: Topology::Square,
+            tiles,
+            rules,
+            sockets: Sockets::default(),
+            count_limits: vec![],
+        })
+    }
+
+    // Loads the model of every tile, indexed by TileId
+    // Tiles with the same model, rotation and mirroring share the loaded model
+    pub fn load_models(&self) -> anyhow::Result<Vec<Vec<Triangle>>> {
+        let model_loader = ModelLoader::new();
+        let mut loaded_models = HashMap::<(&str, usize, bool), Vec<Triangle>>::new();
+
+        self.tiles
+            .iter()
+            .map(|tile| {
+                // Empty tiles, like air between buildings
+                if tile.model == EMPTY_MODEL {
+                    return Ok(vec![]);
+                }
+
+                let key = (tile.model.as_str(), tile.rotation, tile.mirrored);
+                if let Some(model) = loaded_models.get(&key) {
+                    return Ok(model.clone());
+                }
+                let mut model = model_loader.load_gltf_model(
+                    format!("{}/{}", self.path, tile.model),
+                    Rotation3::from_euler_angles(
+                        0.0,
+                        0.0,
+                        tile.rotation as f32 * self.topology.rotation_step(),
+                    ),
+                )?;
+                if tile.mirrored {
+                    model = model.iter().map(|triangle| triangle.mirror()).collect();
+                }
+                loaded_models.insert(key, model.clone());
+                Ok(model)
+            })
+            .collect()
+    }
+}
+
+// Explicit rule for which tiles can be next to each other, on top of matching edges
+// Written as allow, tile, direction, neighbour or deny, tile, direction, neighbour which lets
+// or stops the neighbour from being placed in that direction of the tile. Tiles are written as
+// model for all its rotations or model@rotation, the direction can also be any
+struct TileRule {
+    allow: bool,
+    tile: String,
+    directions: Vec<Direction>,
+    neighbour: String,
+    line: usize,
+}
+
+impl TileRule {
+    fn parse(values: &[String], topology: Topology, line: usize) -> anyhow::Result<Self> {
+        ensure!(
+            values.len() == 4,
+            format!(
+                "On line {} the rule has to be written as {}, tile, direction, neighbour",
+                line, values[0]
+            )
+        );
+        let directions = if values[2] == "any" {
+            topology.iterator().collect()
+        } else {
+            let direction = topology
+                .iterator()
+                .find(|direction| direction_name(*direction) == values[2]);
+            ensure!(
+                direction.is_some(),
+                format!(
+                    "On line {} the direction can only be any or one of {}",
+                    line,
+                    topology
+                        .iterator()
+                        .map(direction_name)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            );
+            vec![direction.unwrap()]
+        };
+
+        Ok(Self {
+            allow: values[0] == "allow",
+            tile: values[1].clone(),
+            directions,
+            neighbour: values[3].clone(),
+            line,
+        })
+    }
+
+    fn apply(&self, tiles: &[TileData], rules: &mut AdjacencyRules) -> anyhow::Result<()> {
+        let tile_ids = find_tiles(tiles, &self.tile, self.line)?;
+        let neighbour_ids = find_tiles(tiles, &self.neighbour, self.line)?;
+        for direction in &self.directions {
+            for tile in &tile_ids {
+                for neighbour in &neighbour_ids {
+                    if self.allow {
+                        rules.allow(*tile, *direction, *neighbour);
+                    } else {
+                        rules.deny(*tile, *direction, *neighbour);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns: every tile with the model, or only the one with the rotation and mirroring after @
+pub fn find_tiles(tiles: &[TileData], name: &str, line: usize) -> anyhow::Result<Vec<TileId>> {
+    let tile_ids = tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| tile.model == name || tile.name() == name)
+        .map(|(tile_id, _)| tile_id)
+        .collect::<Vec<TileId>>();
+    ensure!(
+        !tile_ids.is_empty(),
+        format!("On line {} there is no tile {}", line, name)
+    );
+    Ok(tile_ids)
+}
+
+// Reads a line in the format weights, tile, center|edge|noise, scale, seed|image, path
+// The image is relative to the tileset folder
+fn parse_weight_map(
+    values: &[String],
+    tileset_path: &str,
+    line: usize,
+) -> anyhow::Result<WeightMap> {
+    let usage = format!(
+        "On line {} the weights have to be written as weights, tile, followed by center, edge, noise, scale, seed or image, path",
+        line
+    );
+    ensure!(values.len() >= 3, usage.clone());
+    match (values[2].as_str(), values.len()) {
+        ("center", 3) => Ok(WeightMap::Center),
+        ("edge", 3) => Ok(WeightMap::Edge),
+        ("noise", 4 | 5) => {
+            let scale = values[3].parse::<f32>().ok().filter(|scale| *scale > 0.0);
+            ensure!(
+                scale.is_some(),
+                format!(
+                    "On line {} the noise scale can only be a positive number",
+                    line
+                )
+            );
+            let seed = match values.get(4) {
+                Some(seed) => seed.parse::<u64>().map_err(|_| {
+                    anyhow::format_err!(
+                        "On line {} the noise seed can only be a non negative integer",
+                        line
+                    )
+                })?,
+                None => 0,
+            };
+            Ok(WeightMap::Noise {
+                scale: scale.unwrap(),
+                seed,
+            })
+        }
+        ("image", 4) => weight_map::load_image(&format!("{}/{}", tileset_path, values[3])),
+        _ => anyhow::bail!(usage),
+    }
+}
+
+// Returns: the rotation and mirroring of every variant of a tile, None if the value is invalid
+// The value is a number of rotations, optionally followed by m to also add mirrored versions,
+// or a symmetry class that describes which rotations of the tile look different
+fn parse_variants(value: &str, topology: Topology) -> Option<Vec<(usize, bool)>> {
+    let (rotations, mirrored) = match (value, topology) {
+        ("X", Topology::Square) => (1, false),
+        ("I" | "\\", Topology::Square) => (2, false),
+        ("T" | "L", Topology::Square) => (4, false),
+        ("F", Topology::Square) => (4, true),
+        _ => match value.strip_suffix('m') {
+            Some(rotations) => (rotations.parse::<usize>().ok()?, true),
+            None => (value.parse::<usize>().ok()?, false),
+        },
+    };
+    if rotations == 0 || !topology.directions().len().is_multiple_of(rotations) {
+        return None;
+    }
+
+    let mirrorings: &[bool] = if mirrored { &[false, true] } else { &[false] };
+    Some(
+        mirrorings
+            .iter()
+            .flat_map(|mirrored| (0..rotations).map(|rotation| (rotation, *mirrored)))
+            .collect(),
+    )
+}
+
+// Name of a direction in tiles_config.txt
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::UpRight => "up_right",
+        Direction::Right => "right",
+        Direction::DownRight => "down_right",
+        Direction::Down => "down",
+        Direction::DownLeft => "down_left",
+        Direction::Left => "left",
+        Direction::UpLeft => "up_left",
+        Direction::Top => "top",
+        Direction::Bottom => "bottom",
+    }
+}
+
+// Splits an edge value into the edge type and the optional suffix after the colon
+fn parse_edge(
+    value: &str,
+    direction: Direction,
+    line: usize,
+) -> anyhow::Result<(String, Option<String>)> {
+    let parts = value.split(':').collect::<Vec<&str>>();
+    ensure!(
+        parts.len() <= 2,
+        format!(
+            "On line {} the {:?} value can not have multiple suffixes",
+            line, direction
+        )
+    );
+    Ok((parts[0].to_owned(), parts.get(1).map(|s| s.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tileset;
+    use crate::{
+        tile_count::{Amount, CountLimit},
+        topology::Topology,
+        weight_map::WeightMap,
+        Direction,
+    };
+
+    #[test]
+    fn rotations_shift_edges() {
+        let tileset = Tileset::parse(
+            "# comment\npond1.glb, 1, pond, grass, grass, grass, 4",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        assert_eq!(tileset.tiles.len(), 4);
+        assert_eq!(tileset.tiles[1].rotation, 1);
+        assert_eq!(tileset.tiles[1].up_edge, "grass");
+        assert_eq!(tileset.tiles[1].left_edge, "pond");
+        assert!(Tileset::parse(
+            "pond1.glb, 1, pond, grass, grass, grass, 3",
+            "test",
+            Topology::Square
+        )
+        .is_err());
+        assert!(Tileset::parse(
+            "pond1.glb, 1, pond, grass, grass, grass, 3",
+            "test",
+            Topology::Hex
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn mirrored_variants() {
+        let tileset = Tileset::parse(
+            "shore.glb, 1, shore, water, shore!, sand, 1m\n\
+             deny, shore.glb@0m, any, shore.glb@0",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        // Mirroring swaps left and right and flips asymmetric edges
+        assert_eq!(tileset.tiles.len(), 2);
+        assert!(tileset.tiles[1].mirrored);
+        assert_eq!(tileset.tiles[1].up_edge, "shore!");
+        assert_eq!(tileset.tiles[1].right_edge, "sand");
+        assert_eq!(tileset.tiles[1].down_edge, "shore");
+        assert_eq!(tileset.tiles[1].left_edge, "water");
+        assert!(!tileset.rules.is_allowed(1, Direction::Up, 0));
+
+        for (symmetry, count) in [("X", 1), ("\\", 2), ("T", 4), ("F", 8), ("2m", 4)] {
+            let line = format!("a.glb, 1, a, b, c, d, {}", symmetry);
+            let tileset = Tileset::parse(&line, "test", Topology::Square).unwrap();
+            assert_eq!(tileset.tiles.len(), count);
+        }
+        assert!(Tileset::parse("a.glb, 1, a, b, c, d, e, f, T", "test", Topology::Hex).is_err());
+    }
+
+    #[test]
+    fn rules_change_edge_matches() {
+        let tileset = Tileset::parse(
+            "house.glb, 1, grass, grass, grass, grass, 1\n\
+             water.glb, 1, water, water, water, water, 2\n\
+             allow, house.glb, right, water.glb@1\n\
+             deny, house.glb, any, house.glb",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        assert!(tileset.rules.is_allowed(0, Direction::Right, 2));
+        assert!(tileset.rules.is_allowed(2, Direction::Left, 0));
+        assert!(!tileset.rules.is_allowed(0, Direction::Right, 1));
+        assert!(!tileset.rules.is_allowed(0, Direction::Up, 0));
+        assert!(tileset.rules.is_allowed(1, Direction::Up, 2));
+    }
+
+    #[test]
+    fn asymmetric_sockets() {
+        let tileset = Tileset::parse(
+            "shore.glb, 1, shore, water, shore!, sand, 1\n\
+             road.glb, 1, road_in, grass, road_out, grass, 1\n\
+             end.glb, 1, grass, grass, road_in, grass, 1\n\
+             connect, road_in, road_out",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        // Flipped edges connect to the unflipped version, not to themselves
+        assert!(tileset.rules.is_allowed(0, Direction::Up, 0));
+        assert!(!tileset.rules.is_allowed(0, Direction::Right, 0));
+        assert!(tileset.rules.is_allowed(1, Direction::Up, 1));
+        assert!(!tileset.rules.is_allowed(1, Direction::Up, 2));
+        assert!(Tileset::parse("connect, road_in", "test", Topology::Square).is_err());
+    }
+
+    #[test]
+    fn weight_maps() {
+        let tileset = Tileset::parse(
+            "city.glb, 1, grass, grass, grass, grass, 2\n\
+             forest.glb, 1, grass, grass, grass, grass, 1\n\
+             weights, city.glb@1, center\n\
+             weights, forest.glb, noise, 4, 7",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        assert_eq!(tileset.tiles[0].weight_map, WeightMap::Uniform);
+        assert_eq!(tileset.tiles[1].weight_map, WeightMap::Center);
+        assert_eq!(
+            tileset.tiles[2].weight_map,
+            WeightMap::Noise {
+                scale: 4.0,
+                seed: 7
+            }
+        );
+        for line in [
+            "weights, city.glb",
+            "weights, city.glb, spiral",
+            "weights, city.glb, noise, -1",
+            "weights, castle.glb, edge",
+        ] {
+            let text = format!("city.glb, 1, grass, grass, grass, grass, 1\n{}", line);
+            assert!(Tileset::parse(&text, "test", Topology::Square).is_err());
+        }
+    }
+
+    #[test]
+    fn count_limits() {
+        let tileset = Tileset::parse(
+            "town_hall.glb, 1, grass, grass, grass, grass, 4\n\
+             min, town_hall.glb, 1\n\
+             max, town_hall.glb@2, 10%",
+            "test",
+            Topology::Square,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tileset.count_limits,
+            vec![
+                CountLimit {
+                    tiles: vec![0, 1, 2, 3],
+                    min: Some(Amount::Tiles(1)),
+                    max: None,
+                },
+                CountLimit {
+                    tiles: vec![2],
+                    min: None,
+                    max: Some(Amount::Percent(10.0)),
+                },
+            ]
+        );
+        let tiles = "town_hall.glb, 1, grass, grass, grass, grass, 1\n";
+        for line in [
+            "min, town_hall.glb",
+            "max, town_hall.glb, many",
+            "min, castle.glb, 1",
+        ] {
+            assert!(
+                Tileset::parse(&format!("{}{}", tiles, line), "test", Topology::Square).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let tiles = "house.glb, 1, grass, grass, grass, grass, 1\n";
+        for rule in [
+            "allow, house.glb, right",
+            "allow, house.glb, up_right, house.glb",
+            "deny, house.glb, any, castle.glb",
+            "deny, house.glb@1, any, house.glb",
+        ] {
+            assert!(
+                Tileset::parse(&format!("{}{}", tiles, rule), "test", Topology::Square).is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::f32::consts::PI;
+
+use nalgebra::Vector3;
+
+use crate::Direction;
+
+// Shape of the tiles in a layer, decides which edges a tile has and where its neighbours are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Square,
+    // Hexagons with flat tops, every other column is shifted up half a tile
+    Hex,
+}
+
+// What happens at the edge of the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Open,     // Tiles at the edge of the map can have any edge facing out
+    Fixed,    // Tiles at the edge of the map face out with the border edge
+    Wrapping, // The map wraps around so it can be repeated without seams
+}
+
+impl Topology {
+    // Edges within a layer in clockwise order, starting with up
+    pub fn directions(&self) -> &'static [Direction] {
+        match self {
+            Topology::Square => &[
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+            ],
+            Topology::Hex => &[
+                Direction::Up,
+                Direction::UpRight,
+                Direction::DownRight,
+                Direction::Down,
+                Direction::DownLeft,
+                Direction::UpLeft,
+            ],
+        }
+    }
+
+    // All directions a tile has neighbours in, including the layers above and below
+    pub fn iterator(&self) -> impl Iterator<Item = Direction> {
+        self.directions()
+            .iter()
+            .copied()
+            .chain([Direction::Top, Direction::Bottom])
+    }
+
+    // Angle the model of a tile is turned for each rotation
+    pub fn rotation_step(&self) -> f32 {
+        2.0 * PI / self.directions().len() as f32
+    }
+
+    // Returns: the position next to the given one in a direction, None outside of the map
+    pub fn neighbour(
+        &self,
+        position: Vector3<usize>,
+        direction: Direction,
+        map_size: Vector3<usize>,
+    ) -> Option<Vector3<usize>> {
+        let neighbour = position.cast::<i32>() + self.offset(position, direction)?;
+        let within_grid = neighbour.x >= 0
+            && neighbour.y >= 0
+            && neighbour.z >= 0
+            && neighbour.x < map_size.x as i32
+            && neighbour.y < map_size.y as i32
+            && neighbour.z < map_size.z as i32;
+        within_grid.then(|| neighbour.map(|value| value as usize))
+    }
+
+    // Returns: the position next to the given one in a direction, where the map wraps around
+    // from the right side to the left and from the top row to the bottom. The layers don't wrap
+    // Hex maps need an even width so the shifted columns keep alternating
+    pub fn wrapping_neighbour(
+        &self,
+        position: Vector3<usize>,
+        direction: Direction,
+        map_size: Vector3<usize>,
+    ) -> Option<Vector3<usize>> {
+        let neighbour = position.cast::<i32>() + self.offset(position, direction)?;
+        let within_layers = neighbour.z >= 0 && neighbour.z < map_size.z as i32;
+        within_layers.then(|| {
+            Vector3::new(
+                neighbour.x.rem_euclid(map_size.x as i32) as usize,
+                neighbour.y.rem_euclid(map_size.y as i32) as usize,
+                neighbour.z as usize,
+            )
+        })
+    }
+
+    // Returns: how far away the neighbour in a direction is, None if the edge doesn't exist
+    fn offset(&self, position: Vector3<usize>, direction: Direction) -> Option<Vector3<i32>> {
+        let shifted_column = position.x % 2 == 1;
+        let offset = match (direction, self) {
+            (Direction::Up, _) => Vector3::new(0, 1, 0),
+            (Direction::Down, _) => Vector3::new(0, -1, 0),
+            (Direction::Top, _) => Vector3::new(0, 0, 1),
+            (Direction::Bottom, _) => Vector3::new(0, 0, -1),
+            (Direction::Right, Topology::Square) => Vector3::new(1, 0, 0),
+            (Direction::Left, Topology::Square) => Vector3::new(-1, 0, 0),
+            (Direction::UpRight, Topology::Hex) => Vector3::new(1, shifted_column as i32, 0),
+            (Direction::DownRight, Topology::Hex) => Vector3::new(1, shifted_column as i32 - 1, 0),
+            (Direction::DownLeft, Topology::Hex) => Vector3::new(-1, shifted_column as i32 - 1, 0),
+            (Direction::UpLeft, Topology::Hex) => Vector3::new(-1, shifted_column as i32, 0),
+            _ => return None, // Edge doesn't exist in this topology
+        };
+        Some(offset)
+    }
+
+    // Where the center of a tile ends up in the world. Tiles are one unit between opposite edges
+    // and one unit high
+    pub fn world_position(&self, position: Vector3<usize>) -> Vector3<f32> {
+        let position = position.cast::<f32>();
+        match self {
+            Topology::Square => position,
+            Topology::Hex => Vector3::new(
+                position.x * 3.0_f32.sqrt() / 2.0,
+                position.y + (position.x % 2.0) / 2.0,
+                position.z,
+            ),
+        }
+    }
+}
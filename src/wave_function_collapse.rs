@@ -1,16 +1,33 @@
 use std::{
-    collections::{BTreeSet, VecDeque},
-    f32::consts::PI,
-    fs::File,
-    io::{BufRead, BufReader},
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeSet, BinaryHeap, VecDeque},
+    sync::Arc,
 };
 
-use anyhow::{bail, ensure};
-use nalgebra::{Rotation3, Vector2};
-use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use anyhow::ensure;
+use nalgebra::Vector3;
+use rand::{rngs::StdRng, RngCore};
 
-use crate::{model_loader::ModelLoader, scene::Scene, tile::Tile, tile_data::TileData, Direction};
+use crate::{
+    adjacency::AdjacencyRules,
+    cell_selector::{
+        CellSelector, GrowingSelector, LeastEntropySelector, OrderedSelector, RandomSelector,
+    },
+    config::WfcConfig,
+    grid::Grid,
+    pins::{self, Pin},
+    saved_map::SavedMap,
+    scene::Scene,
+    tile::Tile,
+    tile_count::{self, CountLimit},
+    tile_data::{Sockets, TileData, TileId},
+    tileset::Tileset,
+    topology::{Boundary, Topology},
+    Direction, Triangle,
+};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlacementStrategy {
     Random,
     Growing,
@@ -18,578 +35,1540 @@ pub enum PlacementStrategy {
     LeastEntropy,
 }
 
-pub struct WFC<'a> {
-    scene: &'a mut Scene,
+impl PlacementStrategy {
+    // Returns: a new selector that collapses the tile positions in the order of the strategy
+    pub fn cell_selector(&self) -> Box<dyn CellSelector> {
+        match self {
+            PlacementStrategy::Random => Box::<RandomSelector>::default(),
+            PlacementStrategy::Growing => Box::<GrowingSelector>::default(),
+            PlacementStrategy::Ordered => Box::<OrderedSelector>::default(),
+            PlacementStrategy::LeastEntropy => Box::<LeastEntropySelector>::default(),
+        }
+    }
+}
+
+// The solver only works with the tile descriptions, placing the models is done afterwards
+#[derive(Clone)]
+pub struct WFC<'t> {
+    tile_datas: Cow<'t, [TileData]>, // Owned when the solver has to outlive the tileset
+    topology: Topology,              // Decides which edges of the tile datas are used
+    rules: AdjacencyRules,
+    sockets: Sockets, // Decides which edges connect to the border edge of fixed boundaries
+    count_limits: Vec<CountLimit>,
+    pins: Vec<Pin>,
+}
+
+// How many collapses back the solver can undo when it runs into a contradiction
+const MAX_BACKTRACK_DEPTH: usize = 64;
+// Outdated entries are removed from the entropy queue once it has this many per tile position
+const ENTROPY_ENTRIES_PER_TILE: usize = 4;
+
+// The part of the algorithm state that changes while tiles are collapsed, the changes are
+// recorded so backtracking can undo them. Cell selectors read it to choose the next tile
+// position to collapse
+#[derive(Clone)]
+pub struct Wave {
+    tiles: Vec<Tile>,
+    uncollapsed_tiles: BTreeSet<usize>, // Set of indexes of tiles that haven't been collapsed
+    placed_counts: Vec<usize>,          // How many tiles of each count limit have been placed
+    entropy_queue: BinaryHeap<EntropyEntry>, // Only filled for selectors that use entropy
+    entropy_versions: Vec<u32>,         // Version of the newest entry of each tile in the queue
+    changes: Option<Vec<Change>>,       // Changes of the current collapse, None when not recorded
+    map_size: Vector3<usize>,
+    topology: Topology,
+    boundary: Boundary,
+    start_tiles: Vec<usize>,
+    tie_breaks: Arc<[u32]>, // Random order of tiles with the same entropy, empty when it isn't used
+}
+
+impl Wave {
+    // Indexed like the cells of a Grid
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    // Tile positions that still have to be collapsed
+    pub fn uncollapsed_tiles(&self) -> &BTreeSet<usize> {
+        &self.uncollapsed_tiles
+    }
+
+    pub fn map_size(&self) -> Vector3<usize> {
+        self.map_size
+    }
+
+    // Returns: the index of a tile position in tiles
+    pub fn index(&self, position: Vector3<usize>) -> usize {
+        (position.z * self.map_size.y + position.y) * self.map_size.x + position.x
+    }
+
+    // Tile positions to grow the map from, the pinned ones or else the center one
+    pub fn start_tiles(&self) -> &[usize] {
+        &self.start_tiles
+    }
+
+    // Returns: the position next to the given one in a direction, depending on the boundary
+    pub fn neighbour(
+        &self,
+        position: Vector3<usize>,
+        direction: Direction,
+    ) -> Option<Vector3<usize>> {
+        match self.boundary {
+            Boundary::Wrapping => {
+                self.topology
+                    .wrapping_neighbour(position, direction, self.map_size)
+            }
+            Boundary::Open | Boundary::Fixed => {
+                self.topology.neighbour(position, direction, self.map_size)
+            }
+        }
+    }
+
+    // Returns: indexes of the tile positions next to a tile position
+    pub fn neighbours(&self, tile_index: usize) -> Vec<usize> {
+        self.topology
+            .iterator()
+            .filter_map(|direction| self.neighbour(self.tiles[tile_index].tile_position, direction))
+            .map(|position| self.index(position))
+            .collect()
+    }
+
+    // Returns: the uncollapsed tile position with the least entropy, ties are broken by random.
+    // Always None for selectors that don't use entropy, see CellSelector::uses_entropy
+    pub fn pop_least_entropy(&mut self) -> Option<usize> {
+        while let Some(entry) = self.entropy_queue.pop() {
+            if self.is_current(&entry) {
+                return Some(entry.tile_index);
+            }
+        }
+        None
+    }
+
+    // Adds an entry to the entropy queue that replaces the earlier entries of its tile
+    fn queue(&mut self, entropy: f32, tile_index: usize) {
+        self.entropy_versions[tile_index] += 1;
+        self.entropy_queue.push(EntropyEntry {
+            entropy,
+            tie_break: self.tie_breaks[tile_index],
+            tile_index,
+            version: self.entropy_versions[tile_index],
+        });
+
+        if self.entropy_queue.len() > ENTROPY_ENTRIES_PER_TILE * self.tiles.len() {
+            let mut entropy_queue = std::mem::take(&mut self.entropy_queue);
+            entropy_queue.retain(|entry| self.is_current(entry));
+            self.entropy_queue = entropy_queue;
+        }
+    }
+
+    // Returns: whether the entry is the newest one of a tile that still has to be collapsed
+    fn is_current(&self, entry: &EntropyEntry) -> bool {
+        self.entropy_versions[entry.tile_index] == entry.version
+            && self.uncollapsed_tiles.contains(&entry.tile_index)
+    }
+
+    // Adds a change to the ones of the current collapse, if they are recorded
+    fn record(&mut self, change: Change) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change);
+        }
+    }
+}
+
+// Entropy of a tile when it was queued. Tiles are queued again when their options change,
+// so only the entry with the newest version of a tile is used
+#[derive(Clone, Copy, PartialEq)]
+struct EntropyEntry {
+    entropy: f32,
+    tie_break: u32,
+    tile_index: usize,
+    version: u32,
+}
+
+impl Eq for EntropyEntry {}
+
+// Reversed so the heap gives the least entropy first
+impl Ord for EntropyEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .entropy
+            .total_cmp(&self.entropy)
+            .then(other.tie_break.cmp(&self.tie_break))
+            .then(other.tile_index.cmp(&self.tile_index))
+    }
+}
+
+impl PartialOrd for EntropyEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// A change to the wave that backtracking can undo
+#[derive(Clone)]
+enum Change {
+    Removed(usize, Vec<TileId>),   // Options removed from a tile
+    Collapsed(usize, Vec<TileId>), // A tile was collapsed, with the options it had before
+    Counted(usize),                // A tile of a count limit was placed
+}
+
+// A collapse together with every change it caused, in the order they were made
+struct Decision {
+    tile_index: usize,
+    changes: Vec<Change>,
+}
+
+struct Backtracker {
+    history: VecDeque<Decision>,
+    backtracks_left: u32,
+    contradictions: u32, // Times a collapse left tile positions without options, also without backtracking
+}
+
+impl Backtracker {
+    fn new(max_backtracks: u32) -> Self {
+        Self {
+            history: VecDeque::new(),
+            backtracks_left: max_backtracks,
+            contradictions: 0,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.backtracks_left > 0
+    }
+
+    // The oldest decision is forgotten when the history is full, its changes can't be undone anymore
+    fn push(&mut self, decision: Decision) {
+        if self.history.len() == MAX_BACKTRACK_DEPTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(decision);
+    }
+}
+
+// Everything that changes while one map is generated
+struct Generation {
+    wave: Wave,
+    backtracker: Backtracker,
+    random: StdRng,
+    iterations: u32,
+    max_iterations: u32,
 }
 
-impl<'a> WFC<'a> {
-    pub fn new(scene: &'a mut Scene) -> Self {
-        WFC { scene }
+impl Generation {
+    // Returns: the choosen tile of each position, None where no tile has been placed
+    fn grid(&self) -> Grid<Option<TileId>> {
+        Grid::new(
+            self.wave.map_size,
+            self.wave.tiles.iter().map(|tile| tile.data).collect(),
+        )
+    }
+}
+
+// Generates a map one collapse at a time, for example to show how the map is generated.
+// Every step yields the tile position the selector chose, see WFC::into_stepper
+pub struct Stepper<'t> {
+    wfc: Cow<'t, WFC<'t>>,
+    selector: Box<dyn CellSelector>,
+    generation: Generation,
+}
+
+impl Stepper<'_> {
+    pub fn wave(&self) -> &Wave {
+        &self.generation.wave
+    }
+
+    // Returns: the map so far, the same as place_tiles gives once every step is taken
+    pub fn grid(&self) -> Grid<Option<TileId>> {
+        self.generation.grid()
+    }
+
+    // Returns: how many times a collapse left tile positions without any options so far
+    pub fn contradictions(&self) -> u32 {
+        self.generation.backtracker.contradictions
+    }
+
+    // Returns: the weighted entropy of a tile position, see WFC::entropy
+    pub fn entropy(&self, tile_index: usize) -> f32 {
+        let wave = &self.generation.wave;
+        self.wfc.entropy(&wave.tiles[tile_index], wave.map_size)
+    }
+
+    // Returns: the entropy of a tile position where every tile is equally likely,
+    // no tile position can have more
+    pub fn max_entropy(&self) -> f32 {
+        (self.wfc.tile_datas.len() as f32).ln()
+    }
+}
+
+impl Iterator for Stepper<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.wfc.step(&mut self.generation, self.selector.as_mut())
+    }
+}
+
+impl<'t> WFC<'t> {
+    // Tiles can be placed next to each other when their edges connect
+    pub fn new(tile_datas: impl Into<Cow<'t, [TileData]>>, topology: Topology) -> Self {
+        let tile_datas = tile_datas.into();
+        let sockets = Sockets::from_tiles(&tile_datas);
+        let rules = AdjacencyRules::from_edges(&tile_datas, &sockets);
+        Self::with_rules(tile_datas, topology, rules).with_sockets(sockets)
+    }
+
+    pub fn with_rules(
+        tile_datas: impl Into<Cow<'t, [TileData]>>,
+        topology: Topology,
+        rules: AdjacencyRules,
+    ) -> Self {
+        WFC {
+            tile_datas: tile_datas.into(),
+            topology,
+            rules,
+            sockets: Sockets::default(),
+            count_limits: vec![],
+            pins: vec![],
+        }
+    }
+
+    // Sockets of the tileset, used to check which edges can face the border of fixed boundaries.
+    // Without them an edge only connects to itself
+    pub fn with_sockets(mut self, sockets: Sockets) -> Self {
+        self.sockets = sockets;
+        self
+    }
+
+    // Limits tile positions to chosen tiles before anything is collapsed.
+    // The map is then grown from the pinned tile positions instead of from the center
+    pub fn with_pins(mut self, pins: Vec<Pin>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    // Limits how many times tiles are placed in the map
+    pub fn with_count_limits(mut self, count_limits: Vec<CountLimit>) -> Self {
+        self.count_limits = count_limits;
+        self
     }
 
     // Where the actual Wave Function Collapse logic happens
-    pub fn place_tiles(&mut self) -> anyhow::Result<()> {
-        let (placement_strategy, map_size, max_iterations, mut random, tile_datas) =
-            self.load_tiles()?;
+    // Returns: the choosen tile of each position, None where no tile could be placed
+    pub fn place_tiles(&self, config: &WfcConfig) -> Grid<Option<TileId>> {
+        let mut selector = config.placement_strategy.cell_selector();
+        self.place_tiles_with(config, selector.as_mut())
+    }
+
+    // Same as place_tiles, but the selector chooses the order the tile positions are collapsed
+    // in instead of the placement strategy of the config
+    pub fn place_tiles_with(
+        &self,
+        config: &WfcConfig,
+        selector: &mut dyn CellSelector,
+    ) -> Grid<Option<TileId>> {
+        let mut generation = self.start(config, selector);
+        while self.step(&mut generation, selector).is_some() {}
+        generation.grid()
+    }
 
-        let possible_tiles: Vec<&TileData> = tile_datas.iter().collect();
+    // Returns: a stepper that places the tiles of the config one collapse at a time
+    pub fn into_stepper(self, config: &WfcConfig, selector: Box<dyn CellSelector>) -> Stepper<'t> {
+        let generation = self.start(config, selector.as_ref());
+        Stepper {
+            wfc: Cow::Owned(self),
+            selector,
+            generation,
+        }
+    }
+
+    // Same as into_stepper, but the stepper borrows the solver
+    pub fn stepper(&self, config: &WfcConfig, selector: Box<dyn CellSelector>) -> Stepper<'_> {
+        Stepper {
+            generation: self.start(config, selector.as_ref()),
+            wfc: Cow::Borrowed(self),
+            selector,
+        }
+    }
+
+    // Sets up the wave of a new map, nothing is collapsed yet
+    fn start(&self, config: &WfcConfig, selector: &dyn CellSelector) -> Generation {
+        let map_size = Vector3::<usize>::new(config.map_width, config.map_height, config.map_depth);
+        let mut random = config.random();
+        let cell_count = map_size.x * map_size.y * map_size.z;
+        let tie_breaks = match selector.uses_entropy() {
+            true => (0..cell_count).map(|_| random.next_u32()).collect(),
+            false => Arc::from([]),
+        };
+        // Tiles that may not be placed at all are never an option
+        let possible_tiles: Vec<TileId> = (0..self.tile_datas.len())
+            .filter(|tile| {
+                !self
+                    .count_limits
+                    .iter()
+                    .any(|limit| limit.max_tiles(cell_count) == 0 && limit.tiles.contains(tile))
+            })
+            .collect();
 
         // Fill tiles list with all possibilities
         let mut tiles: Vec<Tile> = vec![];
-        for i in 0..(map_size * map_size) {
+        for i in 0..(map_size.x * map_size.y * map_size.z) {
             tiles.push(Tile::new(
                 possible_tiles.clone(),
-                self.index1dto2d(i, map_size),
+                self.index1dto3d(i, map_size),
             ));
         }
-        // Set of indexes of tiles that haven't been collapsed
-        let mut uncollapsed_tiles: BTreeSet<usize> =
-            (0..(map_size * map_size)).collect::<BTreeSet<usize>>();
-        let mut iterations = 0;
+        // Tiles at the edge of the map can only face out with edges that connect to the border
+        // edge, the same way they would connect to the edge of a neighbour
+        if config.boundary == Boundary::Fixed {
+            for tile in tiles.iter_mut() {
+                let position = tile.tile_position;
+                let outward_directions = self
+                    .topology
+                    .directions()
+                    .iter()
+                    .filter(|direction| {
+                        self.topology
+                            .neighbour(position, **direction, map_size)
+                            .is_none()
+                    })
+                    .collect::<Vec<&Direction>>();
+                tile.possible_tiles.retain(|option| {
+                    outward_directions.iter().all(|direction| {
+                        self.sockets.connects(
+                            self.tile_datas[*option].get_edge(**direction),
+                            &config.border_edge,
+                        )
+                    })
+                });
+            }
+        }
 
-        match placement_strategy {
-            PlacementStrategy::Random => self.random_placement_strategy(
-                &mut tiles,
-                &mut uncollapsed_tiles,
-                &mut iterations,
-                map_size,
-                max_iterations,
-                &mut random,
-            ),
-            PlacementStrategy::Growing => self.growing_placement_strategy(
-                &mut tiles,
-                &mut uncollapsed_tiles,
-                &mut iterations,
-                map_size,
-                max_iterations,
-                &mut random,
-            ),
-            PlacementStrategy::Ordered => self.ordered_placement_strategy(
-                &mut tiles,
-                &mut uncollapsed_tiles,
-                &mut iterations,
-                map_size,
-                max_iterations,
-                &mut random,
-            ),
-            PlacementStrategy::LeastEntropy => self.least_entropy_placement_strategy(
-                &mut tiles,
-                &mut uncollapsed_tiles,
-                &mut iterations,
-                map_size,
-                max_iterations,
-                &mut random,
-            ),
+        // Pins outside the map are ignored
+        for pin in &self.pins {
+            for position in pin.positions(map_size) {
+                tiles[self.index3dto1d(position, map_size)]
+                    .possible_tiles
+                    .retain(|tile| pin.tiles.contains(tile));
+            }
+        }
+        let mut wave = Wave {
+            tiles,
+            uncollapsed_tiles: (0..(map_size.x * map_size.y * map_size.z))
+                .collect::<BTreeSet<usize>>(),
+            placed_counts: vec![0; self.count_limits.len()],
+            entropy_queue: BinaryHeap::new(),
+            entropy_versions: vec![0; cell_count],
+            changes: None,
+            map_size,
+            topology: self.topology,
+            boundary: config.boundary,
+            start_tiles: self.start_tiles(map_size),
+            tie_breaks,
+        };
+
+        // Remove options that can never fit before anything is collapsed
+        for tile_index in self.propagate(
+            &mut wave,
+            0..(map_size.x * map_size.y * map_size.z),
+            map_size,
+        ) {
+            wave.uncollapsed_tiles.remove(&tile_index);
+        }
+        for tile_index in 0..cell_count {
+            self.queue_by_entropy(&mut wave, tile_index, map_size);
+        }
+
+        Generation {
+            wave,
+            backtracker: Backtracker::new(config.max_backtracks),
+            random,
+            iterations: 0,
+            // Every tile position gets an iteration, max_iterations are the ones on top of that
+            max_iterations: (cell_count as u32).saturating_add(config.max_iterations),
+        }
+    }
+
+    // Collapses the tile position the selector chooses
+    // Returns: the chosen tile position, None when the map is done
+    fn step(&self, generation: &mut Generation, selector: &mut dyn CellSelector) -> Option<usize> {
+        let Generation {
+            wave,
+            backtracker,
+            random,
+            iterations,
+            max_iterations,
+        } = generation;
+        if *iterations >= *max_iterations || wave.uncollapsed_tiles.is_empty() {
+            return None;
         }
 
-        Ok(())
+        let tile_index = selector.next_tile(wave, random)?;
+        // Tile positions that aren't uncollapsed anymore still count as an iteration,
+        // so a selector that keeps choosing them can't loop forever
+        let mut undone = 0;
+        if wave.uncollapsed_tiles.remove(&tile_index) {
+            let map_size = wave.map_size;
+            undone = self.collapse_tile(wave, backtracker, tile_index, random, map_size);
+        }
+        // Undone collapses have to be made again, so they don't use up iterations
+        *iterations = (*iterations + 1).saturating_sub(undone);
+        Some(tile_index)
     }
 
+    // Collapses a tile and propagates the change. If a tile ends up without any options
+    // and backtracking is enabled the choice is undone and banned, and another one is tried.
+    // When a tile runs out of options the collapse before it is undone as well.
+    // Returns: how many earlier collapses were undone
     fn collapse_tile(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
+        &self,
+        wave: &mut Wave,
+        backtracker: &mut Backtracker,
         tile_index: usize,
         random: &mut StdRng,
-        map_size: usize,
-    ) {
-        if !tiles[tile_index].collapse(self.scene, random) {
-            return;
-        }
+        map_size: Vector3<usize>,
+    ) -> u32 {
+        let mut tile_index = tile_index;
+        let mut undone = 0;
+        loop {
+            wave.changes = backtracker.enabled().then(Vec::new);
 
-        uncollapsed_tiles.remove(&tile_index);
-
-        // Update neighbours
-        for direction in Direction::iterator() {
-            let neighbour_position = Vector2::<i32>::new(
-                tiles[tile_index].tile_position.x as i32,
-                tiles[tile_index].tile_position.y as i32,
-            ) + direction.get_vector();
-            if !self.within_grid(neighbour_position, map_size) {
-                continue;
+            let bias = self.min_count_bias(wave, tile_index, map_size);
+            let options = wave.tiles[tile_index].possible_tiles.clone();
+            if !wave.tiles[tile_index].collapse(&self.tile_datas, map_size, &bias, random) {
+                wave.changes = None;
+                return undone;
             }
+            wave.record(Change::Collapsed(tile_index, options));
+            wave.uncollapsed_tiles.remove(&tile_index);
 
-            let neighbour_index = self.index2dto1d(
-                Vector2::<usize>::new(neighbour_position.x as usize, neighbour_position.y as usize),
+            let (changed_tiles, mut impossible_tiles) =
+                self.apply_max_counts(wave, tile_index, map_size);
+            impossible_tiles.extend(self.propagate(
+                wave,
+                std::iter::once(tile_index).chain(changed_tiles),
                 map_size,
-            );
-            if let Some(tile_data) = tiles[tile_index].data {
-                if tiles[neighbour_index].remove_options(
-                    direction.get_opposite(),
-                    tile_data.get_edge(direction),
-                    tile_data.get_suffix(direction),
-                ) {
-                    uncollapsed_tiles.remove(&neighbour_index);
+            ));
+            let changes = wave.changes.take();
+            if impossible_tiles.is_empty() {
+                if let Some(changes) = changes {
+                    backtracker.push(Decision {
+                        tile_index,
+                        changes,
+                    });
                 }
+                return undone;
+            }
+            backtracker.contradictions += 1;
+
+            let Some(changes) = changes else {
+                // Out of backtracks, leave the impossible tiles empty
+                for impossible_index in impossible_tiles {
+                    wave.uncollapsed_tiles.remove(&impossible_index);
+                }
+                return undone;
+            };
+            backtracker.backtracks_left -= 1;
+
+            // Undo the choice and make sure it isn't picked again
+            let choosen_tile = wave.tiles[tile_index]
+                .data
+                .expect("Tile was just collapsed");
+            self.undo(wave, changes, map_size);
+            let mut out_of_options = Self::ban(wave, backtracker, tile_index, choosen_tile);
+
+            // Every option of this tile fails, so undo the previous collapse instead
+            while out_of_options {
+                let Some(previous) = backtracker.history.pop_back() else {
+                    // Nothing left to undo, the tile stays empty
+                    wave.uncollapsed_tiles.remove(&tile_index);
+                    return undone;
+                };
+                // The tile has to be collapsed again after the previous one
+                wave.uncollapsed_tiles.insert(tile_index);
+                let choosen_tile = wave.tiles[previous.tile_index]
+                    .data
+                    .expect("Tile in history is collapsed");
+                self.undo(wave, previous.changes, map_size);
+                undone += 1;
+                tile_index = previous.tile_index;
+                out_of_options = Self::ban(wave, backtracker, tile_index, choosen_tile);
             }
         }
     }
 
-    fn collapse_center_tile(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
-        random: &mut StdRng,
-        map_size: usize,
-    ) {
-        let center_tile_index =
-            self.index2dto1d(Vector2::<usize>::new(map_size / 2, map_size / 2), map_size);
-        self.collapse_tile(
-            tiles,
-            uncollapsed_tiles,
-            center_tile_index,
-            random,
-            map_size,
-        );
-        uncollapsed_tiles.remove(&center_tile_index);
+    // Puts the wave back the way it was before the changes, the newest change is undone first
+    fn undo(&self, wave: &mut Wave, changes: Vec<Change>, map_size: Vector3<usize>) {
+        let mut changed_tiles = BTreeSet::new();
+        for change in changes.into_iter().rev() {
+            match change {
+                Change::Removed(tile_index, options) => {
+                    wave.tiles[tile_index].restore_options(&options);
+                    changed_tiles.insert(tile_index);
+                }
+                Change::Collapsed(tile_index, options) => {
+                    let tile = &mut wave.tiles[tile_index];
+                    tile.data = None;
+                    tile.possible_tiles = options;
+                    changed_tiles.insert(tile_index);
+                }
+                Change::Counted(limit_index) => wave.placed_counts[limit_index] -= 1,
+            }
+        }
+        for tile_index in changed_tiles {
+            self.queue_by_entropy(wave, tile_index, map_size);
+        }
     }
 
-    fn load_tiles(
-        &self,
-    ) -> anyhow::Result<(&PlacementStrategy, usize, u32, StdRng, Vec<TileData>)> {
-        let mut placement_strategy: &PlacementStrategy = &PlacementStrategy::LeastEntropy;
-        let mut map_size: usize = 10;
-        let mut max_iterations: u32 = 1000;
-        let mut tileset_path: String = "".to_owned();
-        let mut seed: u64 = 0;
-
-        self.read_config_file(
-            &mut placement_strategy,
-            &mut map_size,
-            &mut max_iterations,
-            &mut tileset_path,
-            &mut seed,
-        )?;
-
-        let tiles = self.read_tileset_config_file(&mut tileset_path)?;
-
-        Ok((
-            placement_strategy,
-            map_size,
-            max_iterations,
-            if seed == 0 {
-                StdRng::from_entropy()
-            } else {
-                StdRng::seed_from_u64(seed)
-            },
-            tiles,
-        ))
+    // Removes a choice that led to a contradiction. The ban belongs to the collapse before,
+    // so it is lifted again when that collapse is undone
+    // Returns: whether the tile has run out of options
+    fn ban(
+        wave: &mut Wave,
+        backtracker: &mut Backtracker,
+        tile_index: usize,
+        tile: TileId,
+    ) -> bool {
+        let out_of_options = wave.tiles[tile_index].ban(tile);
+        if let Some(previous) = backtracker.history.back_mut() {
+            previous
+                .changes
+                .push(Change::Removed(tile_index, vec![tile]));
+        }
+        out_of_options
     }
 
-    fn read_tileset_config_file(&self, tileset_path: &mut String) -> anyhow::Result<Vec<TileData>> {
-        let mut tiles: Vec<TileData> = vec![];
-        let model_loader = ModelLoader::new();
+    // Returns: the weight multiplier of every tile at the tile position so that the minimum
+    // counts are likely to be reached, see tile_count::min_count_bias
+    fn min_count_bias(&self, wave: &Wave, tile_index: usize, map_size: Vector3<usize>) -> Vec<f32> {
+        if self.count_limits.is_empty() {
+            return vec![1.0; self.tile_datas.len()];
+        }
+        let position = wave.tiles[tile_index].tile_position;
+        let weights = self
+            .tile_datas
+            .iter()
+            .map(|tile_data| tile_data.weight_at(position, map_size))
+            .collect::<Vec<f32>>();
+        // The tile is still counted as remaining when it is collapsed outside of the set
+        let remaining =
+            wave.uncollapsed_tiles.len() + !wave.uncollapsed_tiles.contains(&tile_index) as usize;
+        tile_count::min_count_bias(
+            &self.count_limits,
+            &wave.placed_counts,
+            &wave.tiles[tile_index].possible_tiles,
+            &weights,
+            remaining,
+            wave.tiles.len(),
+        )
+    }
 
-        let file = File::open(format!("{}/tiles_config.txt", tileset_path)).map_err(|_| {
-            anyhow::format_err!(
-                "Could not find config file {}/tiles_config.txt",
-                tileset_path
-            )
-        })?;
+    // Counts the tile that was just collapsed and removes the tiles that have reached their
+    // maximum count from every uncollapsed tile
+    // Returns: indexes of tiles that lost options and of tiles that have become impossible to collapse
+    fn apply_max_counts(
+        &self,
+        wave: &mut Wave,
+        tile_index: usize,
+        map_size: Vector3<usize>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let (mut changed_tiles, mut impossible_tiles) = (vec![], vec![]);
+        let Some(placed_tile) = wave.tiles[tile_index].data else {
+            return (changed_tiles, impossible_tiles);
+        };
 
-        let reader = BufReader::new(file);
-        for (index, line) in reader.lines().enumerate() {
-            let line = line?;
-            // Ignore comments
-            if line.starts_with('#') {
+        for (limit_index, limit) in self.count_limits.iter().enumerate() {
+            if !limit.tiles.contains(&placed_tile) {
+                continue;
+            }
+            wave.placed_counts[limit_index] += 1;
+            wave.record(Change::Counted(limit_index));
+            if wave.placed_counts[limit_index] < limit.max_tiles(wave.tiles.len()) {
                 continue;
             }
 
-            // Validate inputs
-            let values = line
-                .replace(' ', "")
-                .split(',')
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-            ensure!(
-                values.len() == 7,
-                format!(
-                    "Line {} \"{}\" does not contain all values required",
-                    index + 1,
-                    line
-                )
-            );
-            let weight = values[1].parse::<u32>();
-            ensure!(
-                weight.is_ok() && weight.clone().unwrap() > 0,
-                format!(
-                    "On line {} the weight value can only be a positive integer",
-                    index + 1
-                )
-            );
-            let weight = weight.unwrap();
-            ensure!(
-                values[6] == "1" || values[6] == "2" || values[6] == "4",
-                format!(
-                    "On line {} the rotatable value can only be 1, 2 or 4",
-                    index + 1
-                )
-            );
-
-            // Load models
-            let rotation_angles: Vec<Rotation3<f32>> = match values[6].as_str() {
-                "4" => vec![
-                    Rotation3::from_euler_angles(0.0, 0.0, 0.0),
-                    Rotation3::from_euler_angles(0.0, 0.0, PI / 2.0),
-                    Rotation3::from_euler_angles(0.0, 0.0, PI),
-                    Rotation3::from_euler_angles(0.0, 0.0, 3.0 / 2.0 * PI),
-                ],
-                "2" => vec![
-                    Rotation3::from_euler_angles(0.0, 0.0, 0.0),
-                    Rotation3::from_euler_angles(0.0, 0.0, PI / 2.0),
-                ],
-                _ => vec![Rotation3::from_euler_angles(0.0, 0.0, 0.0)],
-            };
+            for index in 0..wave.tiles.len() {
+                let tile = &mut wave.tiles[index];
+                if tile.data.is_some() || tile.possible_tiles.is_empty() {
+                    continue;
+                }
+                let removed = tile.remove_options_where(|option| limit.tiles.contains(&option));
+                if removed.is_empty() {
+                    continue;
+                }
+                if tile.possible_tiles.is_empty() {
+                    if wave.uncollapsed_tiles.contains(&index) {
+                        impossible_tiles.push(index);
+                    }
+                } else {
+                    changed_tiles.push(index);
+                }
+                wave.record(Change::Removed(index, removed));
+            }
+        }
+        for changed_tile in &changed_tiles {
+            self.queue_by_entropy(wave, *changed_tile, map_size);
+        }
 
-            // Store each rotation as seperate model
-            for (index, rotation) in rotation_angles.into_iter().enumerate() {
-                let model = model_loader
-                    .load_gltf_model(format!("{}/{}", tileset_path, &values[0]), rotation)?;
-                let up_string = values[2 + (index % 4)]
-                    .clone()
-                    .split(':')
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-                let right_string = values[2 + ((index + 1) % 4)]
-                    .clone()
-                    .split(':')
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-                let down_string = values[2 + ((index + 2) % 4)]
-                    .clone()
-                    .split(':')
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-                let left_string = values[2 + ((index + 3) % 4)]
-                    .clone()
-                    .split(':')
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-
-                ensure!(
-                    up_string.len() <= 2,
-                    format!(
-                        "On line {} the up value can not have multiple suffixes",
-                        index + 1
-                    )
-                );
-                ensure!(
-                    right_string.len() <= 2,
-                    format!(
-                        "On line {} the right value can not have multiple suffixes",
-                        index + 1
-                    )
-                );
-                ensure!(
-                    down_string.len() <= 2,
-                    format!(
-                        "On line {} the down value can not have multiple suffixes",
-                        index + 1
-                    )
-                );
-                ensure!(
-                    left_string.len() <= 2,
-                    format!(
-                        "On line {} the left value can not have multiple suffixes",
-                        index + 1
-                    )
-                );
+        (changed_tiles, impossible_tiles)
+    }
 
-                let tile = TileData {
-                    model,
-                    weight,
-                    up_edge: up_string[0].to_owned(),
-                    right_edge: right_string[0].to_owned(),
-                    down_edge: down_string[0].to_owned(),
-                    left_edge: left_string[0].to_owned(),
-                    up_edge_suffix: if up_string.len() > 1 {
-                        Some(up_string[1].to_owned())
-                    } else {
-                        None
-                    },
-                    right_edge_suffix: if right_string.len() > 1 {
-                        Some(right_string[1].to_owned())
-                    } else {
-                        None
-                    },
-                    down_edge_suffix: if down_string.len() > 1 {
-                        Some(down_string[1].to_owned())
-                    } else {
-                        None
-                    },
-                    left_edge_suffix: if left_string.len() > 1 {
-                        Some(left_string[1].to_owned())
-                    } else {
-                        None
-                    },
-                };
-                tiles.push(tile);
-            }
+    // Weighted Shannon entropy of the options of a tile, tiles where one option is much more
+    // likely than the others have less entropy than tiles with evenly weighted options
+    fn entropy(&self, tile: &Tile, map_size: Vector3<usize>) -> f32 {
+        let weights = tile
+            .possible_tiles
+            .iter()
+            .map(|option| self.tile_datas[*option].weight_at(tile.tile_position, map_size))
+            .filter(|weight| *weight > 0.0)
+            .collect::<Vec<f32>>();
+        let total_weight = weights.iter().sum::<f32>();
+        if total_weight <= 0.0 {
+            return 0.0;
         }
+        total_weight.ln()
+            - weights
+                .iter()
+                .map(|weight| weight * weight.ln())
+                .sum::<f32>()
+                / total_weight
+    }
 
-        Ok(tiles)
+    // Adds the current entropy of a tile to the queue of the least entropy strategy
+    fn queue_by_entropy(&self, wave: &mut Wave, tile_index: usize, map_size: Vector3<usize>) {
+        if wave.tie_breaks.is_empty() {
+            return;
+        }
+        let entropy = self.entropy(&wave.tiles[tile_index], map_size);
+        wave.queue(entropy, tile_index);
     }
 
-    fn read_config_file(
+    // Spreads the options removed from the changed tiles through the grid (AC-3)
+    // Every tile only keeps the options that can be placed next to at least one of the
+    // remaining options of each neighbour. When a tile loses options its neighbours are revisited.
+    // Returns: indexes of tiles that have become impossible to collapse
+    fn propagate(
         &self,
-        placement_strategy: &mut &PlacementStrategy,
-        map_size: &mut usize,
-        max_iterations: &mut u32,
-        tileset_path: &mut String,
-        seed: &mut u64,
-    ) -> anyhow::Result<()> {
-        const CONFIG_FILE_PATH: &str = "./config.txt";
-
-        let file = File::open(CONFIG_FILE_PATH)
-            .map_err(|_| anyhow::format_err!("Could not find config file {}", CONFIG_FILE_PATH))?;
-
-        // Read values
-        let reader = BufReader::new(file);
-        for (index, line) in reader.lines().enumerate() {
-            let line = line?;
-
-            // Ignore comments
-            if line.starts_with('#') {
+        wave: &mut Wave,
+        changed_tiles: impl IntoIterator<Item = usize>,
+        map_size: Vector3<usize>,
+    ) -> Vec<usize> {
+        let mut impossible_tiles = vec![];
+        let mut tiles_queue = VecDeque::<usize>::from_iter(changed_tiles);
+        let mut queued_tiles = vec![false; wave.tiles.len()];
+        for tile_index in tiles_queue.iter() {
+            queued_tiles[*tile_index] = true;
+        }
+
+        while let Some(tile_index) = tiles_queue.pop_front() {
+            queued_tiles[tile_index] = false;
+
+            let options = wave.tiles[tile_index].options();
+            // Impossible tiles don't constrain their neighbours
+            if options.is_empty() {
                 continue;
             }
-            let parts = line
-                .replace(' ', "")
-                .split('=')
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            ensure!(parts.len() == 2 && parts[0].as_str() != "" && parts[1].as_str() != "", format!("Error in {} on line {}. The config file accepts lines in the format of KEY=VALUE", CONFIG_FILE_PATH, index + 1));
-
-            // Read and validate options
-            match parts[0].as_str() {
-                "placement_strategy" => {
-                    match parts[1].as_str() {
-                        "least_entropy" => *placement_strategy = &PlacementStrategy::LeastEntropy,
-                        "random" => *placement_strategy = &PlacementStrategy::Random,
-                        "ordered" => *placement_strategy = &PlacementStrategy::Ordered,
-                        "growing" => *placement_strategy = &PlacementStrategy::Growing,
-                        _ => bail!(
-                            format!(
-                                "On line {} the placement_strategy can only be least_entropy, random, ordered or growing",
-                                index + 1
-                            )
-                        )
+
+            for direction in self.topology.iterator() {
+                let Some(neighbour_position) =
+                    wave.neighbour(wave.tiles[tile_index].tile_position, direction)
+                else {
+                    continue;
+                };
+
+                let neighbour_index = self.index3dto1d(neighbour_position, map_size);
+                let neighbour_tile = &mut wave.tiles[neighbour_index];
+                if neighbour_tile.data.is_some() || neighbour_tile.possible_tiles.is_empty() {
+                    continue;
+                }
+
+                let removed = neighbour_tile.remove_unsupported_options(
+                    &self.rules,
+                    direction.get_opposite(),
+                    &options,
+                );
+                if removed.is_empty() {
+                    continue;
+                }
+                let out_of_options = neighbour_tile.possible_tiles.is_empty();
+                wave.record(Change::Removed(neighbour_index, removed));
+
+                if out_of_options {
+                    if wave.uncollapsed_tiles.contains(&neighbour_index) {
+                        impossible_tiles.push(neighbour_index);
                     }
+                    continue;
+                }
+                self.queue_by_entropy(wave, neighbour_index, map_size);
+                if !queued_tiles[neighbour_index] {
+                    queued_tiles[neighbour_index] = true;
+                    tiles_queue.push_back(neighbour_index);
                 }
-                "tile_set" => tileset_path.clone_from(&parts[1]),
-                "map_size" => {
-                    let max = parts[1].parse::<usize>().map_err(|_| anyhow::format_err!(
-                        "Error in {} on line {}. {} is not a valid number",
-                        CONFIG_FILE_PATH,
-                        index + 1,
-                        parts[1]
-                    ))?;
-
-                    ensure!((1..=100).contains(&max), format!(
-                        "Error in {} on line {}. {} is not a accepted number. It has to be between 1 and 100",
-                        CONFIG_FILE_PATH,
-                        index + 1,
-                        parts[1]
-                    ));
-
-                    *map_size = max;
-                },
-                "max_iterations" =>  {
-                    let max = parts[1].parse::<u32>().map_err(|_| anyhow::format_err!(
-                        "Error in {} on line {}. {} is not a valid number",
-                        CONFIG_FILE_PATH,
-                        index + 1,
-                        parts[1]
-                    ))?;
-
-                    ensure!((100..=10000).contains(&max), format!(
-                        "Error in {} on line {}. {} is not a accepted number. It has to be between 100 and 10000",
-                        CONFIG_FILE_PATH,
-                        index + 1,
-                        parts[1]
-                    ));
-
-                    *max_iterations = max;
-                },
-                "seed" => {
-                    let input_seed = parts[1].parse::<u64>().map_err(|_| anyhow::format_err!(
-                        "Error in {} on line {}. {} is not a valid number",
-                        CONFIG_FILE_PATH,
-                        index + 1,
-                        parts[1]
-                    ))?;
-                    *seed = input_seed;
-                },
-                _ => bail!(format!(
-                    "Error in {} on line {}. {} is not a option",
-                    CONFIG_FILE_PATH,
-                    index + 1,
-                    parts[0]
-                )),
             }
         }
 
-        // Make sure obligatory options have been set
-        if tileset_path.as_str() == "" {
-            bail!(format!(
-                "Error in {}. Option tileset_path has not been set",
-                CONFIG_FILE_PATH,
-            ))
+        impossible_tiles
+    }
+
+    // Returns: indexes of the pinned tile positions, or of the center tile position if there are no pins
+    fn start_tiles(&self, map_size: Vector3<usize>) -> Vec<usize> {
+        let pinned_tiles = self
+            .pins
+            .iter()
+            .flat_map(|pin| pin.positions(map_size))
+            .map(|position| self.index3dto1d(position, map_size))
+            .collect::<BTreeSet<usize>>();
+        if !pinned_tiles.is_empty() {
+            return pinned_tiles.into_iter().collect();
         }
 
-        Ok(())
+        vec![self.index3dto1d(
+            Vector3::<usize>::new(map_size.x / 2, map_size.y / 2, 0),
+            map_size,
+        )]
     }
 
-    fn index2dto1d(&self, index: Vector2<usize>, map_size: usize) -> usize {
-        index.y * map_size + index.x
+    fn index3dto1d(&self, index: Vector3<usize>, map_size: Vector3<usize>) -> usize {
+        (index.z * map_size.y + index.y) * map_size.x + index.x
     }
 
-    fn index1dto2d(&self, index: usize, map_size: usize) -> Vector2<usize> {
-        Vector2::<usize>::new(index % map_size, index / map_size)
+    fn index1dto3d(&self, index: usize, map_size: Vector3<usize>) -> Vector3<usize> {
+        Vector3::<usize>::new(
+            index % map_size.x,
+            index / map_size.x % map_size.y,
+            index / (map_size.x * map_size.y),
+        )
     }
+}
+
+// A tileset together with the models of its tiles, for placing the models of generated maps
+pub struct LoadedTileset {
+    pub tileset: Tileset,
+    pub models: Vec<Vec<Triangle>>, // Indexed by TileId
+}
 
-    fn within_grid(&self, index: Vector2<i32>, map_size: usize) -> bool {
-        index.x >= 0 && index.y >= 0 && index.x < map_size as i32 && index.y < map_size as i32
+impl LoadedTileset {
+    // Reads the tileset of the config and the models of its tiles
+    pub fn load(config: &WfcConfig) -> anyhow::Result<Self> {
+        let tileset = load_tileset(config)?;
+        let models = tileset.load_models()?;
+        Ok(Self { tileset, models })
     }
 
-    /** PLACEMENT STRATEGIES **/
-    // Chooses random tile to collapse
-    fn random_placement_strategy(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
-        iterations: &mut u32,
-        map_size: usize,
-        max_iterations: u32,
-        random: &mut StdRng,
-    ) {
-        self.collapse_center_tile(tiles, uncollapsed_tiles, random, map_size);
+    // Every tile gets an empty model, for maps that are generated but not shown
+    pub fn without_models(tileset: Tileset) -> Self {
+        let models = vec![vec![]; tileset.tiles.len()];
+        Self { tileset, models }
+    }
+}
 
-        while *iterations < max_iterations && !uncollapsed_tiles.is_empty() {
-            let choosen_tile = *uncollapsed_tiles
-                .iter()
-                .choose(random)
-                .expect("Set is not empty");
-            uncollapsed_tiles.remove(&choosen_tile);
-            self.collapse_tile(tiles, uncollapsed_tiles, choosen_tile, random, map_size);
-            *iterations += 1;
-        }
-    }
-
-    // Collapses tiles in the order of a BFS from the starting tile
-    fn growing_placement_strategy(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
-        iterations: &mut u32,
-        map_size: usize,
-        max_iterations: u32,
-        random: &mut StdRng,
+// Reads the tileset of the config, or learns it from the example if one is set
+pub fn load_tileset(config: &WfcConfig) -> anyhow::Result<Tileset> {
+    ensure!(
+        config.tileset_path.as_str() != "",
+        "No tileset has been set in the config"
+    );
+    if config.example_path.is_empty() {
+        return Tileset::load(&config.tileset_path, config.topology);
+    }
+    ensure!(
+        config.topology == Topology::Square,
+        "Tiles can only be learned from an example with the square topology"
+    );
+    Tileset::from_example(
+        &config.tileset_path,
+        &config.example_path,
+        config.example_model,
+        config.pattern_size,
+    )
+}
+
+// Returns: a solver with the rules and count limits of the tileset and the pins of the config
+pub fn create_solver(config: &WfcConfig, tileset: Tileset) -> anyhow::Result<WFC<'static>> {
+    let pins = if config.pins_path.is_empty() {
+        vec![]
+    } else {
+        pins::load_pins(&config.pins_path, &tileset.tiles)?
+    };
+
+    Ok(
+        WFC::with_rules(tileset.tiles, tileset.topology, tileset.rules)
+            .with_sockets(tileset.sockets)
+            .with_count_limits(tileset.count_limits)
+            .with_pins(pins),
+    )
+}
+
+// Generates a map and places the models of the choosen tiles in a new scene
+pub fn generate_scene(config: &WfcConfig) -> anyhow::Result<Scene> {
+    generate_map(config).map(|(scene, _)| scene)
+}
+
+// Generates a map like generate_scene
+// Returns: the scene and the map, which has the seed that was used when the config has none
+pub fn generate_map(config: &WfcConfig) -> anyhow::Result<(Scene, SavedMap)> {
+    let config = WfcConfig {
+        seed: config.pick_seed(),
+        ..config.clone()
+    };
+    let LoadedTileset { tileset, models } = LoadedTileset::load(&config)?;
+    let grid = create_solver(&config, tileset.clone())?.place_tiles(&config);
+
+    let mut scene = Scene::new();
+    scene.instantiate_grid(&grid, &models, tileset.topology, Vector3::zeros());
+    Ok((scene, SavedMap::new(&config, config.seed, &grid, &tileset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use rand::rngs::StdRng;
+
+    use super::{PlacementStrategy, Wave, ENTROPY_ENTRIES_PER_TILE, WFC};
+    use crate::{
+        adjacency::AdjacencyRules,
+        cell_selector::CellSelector,
+        config::WfcConfig,
+        grid::Grid,
+        pins::{Pin, PinArea},
+        test_tiles::{pond_tiles, three_colors, tile},
+        tile::Tile,
+        tile_count::{Amount, CountLimit},
+        tile_data::{Sockets, TileData, TileId},
+        topology::{Boundary, Topology},
+        weight_map::WeightMap,
+        Direction,
+    };
+
+    // Rivers that enter a hex on one edge and leave on the opposite one, in all 3 rotations
+    fn hex_river_tiles() -> Vec<TileData> {
+        let mut tile_datas = vec![TileData {
+            weight: 8,
+            ..Default::default()
+        }];
+        for rotation in 0..3 {
+            let mut river = TileData {
+                weight: 1,
+                rotation,
+                ..Default::default()
+            };
+            for (index, direction) in Topology::Hex.directions().iter().enumerate() {
+                let edge = if index % 3 == rotation { "river" } else { "" };
+                river.set_edge(*direction, edge.to_owned(), None);
+            }
+            tile_datas.push(river);
+        }
+        tile_datas
+    }
+
+    // Buildings with a roof and air above them. Nothing has a bedrock top edge, so ground only
+    // fits in the lowest layer
+    fn layer_tiles() -> Vec<TileData> {
+        [
+            ("open", "bedrock"), // Ground
+            ("open", "open"),    // Building
+            ("sky", "open"),     // Roof
+            ("sky", "sky"),      // Air
+        ]
+        .into_iter()
+        .map(|(top_edge, bottom_edge)| TileData {
+            top_edge: top_edge.to_owned(),
+            bottom_edge: bottom_edge.to_owned(),
+            ..tile(1, ["street", "street", "street", "street"])
+        })
+        .collect()
+    }
+
+    fn assert_valid(
+        tile_datas: &[TileData],
+        grid: &Grid<Option<TileId>>,
+        topology: Topology,
+        boundary: Boundary,
     ) {
-        let mut tiles_queue = VecDeque::<usize>::new();
-        tiles_queue.push_back(
-            self.index2dto1d(Vector2::<usize>::new(map_size / 2, map_size / 2), map_size),
+        for (position, tile) in grid.iter() {
+            let tile = tile.expect("Every position has a tile");
+            for direction in topology.iterator() {
+                let neighbour_position = match boundary {
+                    Boundary::Wrapping => {
+                        topology.wrapping_neighbour(position, direction, grid.size())
+                    }
+                    Boundary::Open | Boundary::Fixed => {
+                        topology.neighbour(position, direction, grid.size())
+                    }
+                };
+                let Some(neighbour_position) = neighbour_position else {
+                    continue;
+                };
+                let neighbour = grid
+                    .get(neighbour_position)
+                    .expect("Every position has a tile");
+                assert!(tile_datas[neighbour].check_edge(
+                    direction.get_opposite(),
+                    tile_datas[tile].get_edge(direction),
+                    tile_datas[tile].get_suffix(direction),
+                    &Sockets::default(),
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn all_strategies_fill_map() {
+        let tile_datas = pond_tiles();
+        for placement_strategy in [
+            PlacementStrategy::Random,
+            PlacementStrategy::Growing,
+            PlacementStrategy::Ordered,
+            PlacementStrategy::LeastEntropy,
+        ] {
+            for seed in 1..=200 {
+                let config = WfcConfig::builder()
+                    .placement_strategy(placement_strategy)
+                    .map_size(12)
+                    .max_backtracks(100)
+                    .seed(seed)
+                    .build()
+                    .unwrap();
+                let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+                assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+            }
+        }
+    }
+
+    #[test]
+    fn rectangular_map() {
+        let tile_datas = pond_tiles();
+        for placement_strategy in [
+            PlacementStrategy::Random,
+            PlacementStrategy::Growing,
+            PlacementStrategy::Ordered,
+            PlacementStrategy::LeastEntropy,
+        ] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .map_width(80)
+                .map_height(8)
+                .max_backtracks(100)
+                .seed(3)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+            assert_eq!((grid.width(), grid.height()), (80, 8));
+            assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+        }
+    }
+
+    #[test]
+    fn stacked_layers() {
+        let tile_datas = layer_tiles();
+
+        for placement_strategy in [
+            PlacementStrategy::Random,
+            PlacementStrategy::Growing,
+            PlacementStrategy::Ordered,
+            PlacementStrategy::LeastEntropy,
+        ] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .map_size(6)
+                .map_depth(4)
+                .max_backtracks(100)
+                .seed(5)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+            assert_eq!(grid.depth(), 4);
+            assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+            for (position, tile) in grid.iter() {
+                if *tile == Some(0) {
+                    assert_eq!(position.z, 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deep_map_is_filled_with_default_iterations() {
+        let tile_datas = layer_tiles();
+        // 2000 tile positions, more than the default max_iterations
+        let config = WfcConfig::builder().map_depth(20).seed(7).build().unwrap();
+        let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+    }
+
+    #[test]
+    fn hex_map() {
+        let tile_datas = hex_river_tiles();
+
+        for placement_strategy in [
+            PlacementStrategy::Random,
+            PlacementStrategy::Growing,
+            PlacementStrategy::Ordered,
+            PlacementStrategy::LeastEntropy,
+        ] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .topology(Topology::Hex)
+                .map_width(9)
+                .map_height(7)
+                .max_backtracks(100)
+                .seed(2)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Hex).place_tiles(&config);
+
+            assert_valid(&tile_datas, &grid, Topology::Hex, Boundary::Open);
+        }
+    }
+
+    #[test]
+    fn weight_maps_vary_by_position() {
+        let mut city = tile(1, ["grass", "grass", "grass", "grass"]);
+        city.weight_map = WeightMap::Center;
+        let mut forest = tile(1, ["grass", "grass", "grass", "grass"]);
+        forest.weight_map = WeightMap::Edge;
+        let tile_datas = vec![city, forest];
+        let config = WfcConfig::builder().map_size(20).seed(2).build().unwrap();
+        let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        // Count the city tiles in the middle and in the outer ring of the map
+        let (mut inner_cities, mut outer_cities) = (0, 0);
+        for (position, tile) in grid.iter() {
+            let is_city = tile.expect("Every position has a tile") == 0;
+            if (5..15).contains(&position.x) && (5..15).contains(&position.y) {
+                inner_cities += is_city as u32;
+            } else if !(2..18).contains(&position.x) || !(2..18).contains(&position.y) {
+                outer_cities += is_city as u32;
+            }
+        }
+        // The middle has 100 tiles and the outer ring 144
+        assert!(
+            inner_cities * 144 > 2 * outer_cities * 100,
+            "{} cities in the middle and {} at the edge",
+            inner_cities,
+            outer_cities
         );
+    }
+
+    #[test]
+    fn count_limits_are_kept() {
+        let tile_datas = vec![
+            tile(16, ["grass", "grass", "grass", "grass"]),
+            tile(1, ["grass", "grass", "grass", "grass"]),
+            tile(1, ["grass", "grass", "grass", "grass"]),
+            tile(1, ["grass", "grass", "grass", "grass"]),
+        ];
+        let limit = |tiles: Vec<TileId>, min: Option<Amount>, max: Option<Amount>| CountLimit {
+            tiles,
+            min,
+            max,
+        };
+        let count_limits = vec![
+            limit(vec![0], None, Some(Amount::Tiles(5))),
+            limit(vec![1], Some(Amount::Percent(40.0)), None),
+            limit(vec![2], Some(Amount::Tiles(1)), Some(Amount::Tiles(1))),
+            limit(vec![3], None, Some(Amount::Tiles(0))),
+        ];
+
+        for placement_strategy in [PlacementStrategy::LeastEntropy, PlacementStrategy::Ordered] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .seed(6)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square)
+                .with_count_limits(count_limits.clone())
+                .place_tiles(&config);
+
+            let count = |tile_id: TileId| {
+                grid.cells()
+                    .iter()
+                    .filter(|tile| **tile == Some(tile_id))
+                    .count()
+            };
+            assert!(count(0) <= 5);
+            assert!(count(1) >= 40);
+            assert_eq!(count(2), 1);
+            assert_eq!(count(3), 0);
+        }
+    }
+
+    #[test]
+    fn pinned_tiles_are_kept() {
+        let tile_datas = pond_tiles();
+        // A river along column 4 and a single pond end at (2, 7)
+        let pins = vec![
+            Pin {
+                area: PinArea::Column(4),
+                tiles: vec![5],
+            },
+            Pin {
+                area: PinArea::Tile(Vector3::new(2, 7, 0)),
+                tiles: vec![3],
+            },
+        ];
 
-        while !tiles_queue.is_empty()
-            && *iterations < max_iterations
-            && !uncollapsed_tiles.is_empty()
-        {
-            let choosen_tile = tiles_queue.pop_front().expect("Queue is not empty");
-            uncollapsed_tiles.remove(&choosen_tile);
+        for placement_strategy in [PlacementStrategy::LeastEntropy, PlacementStrategy::Growing] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .max_backtracks(100)
+                .seed(8)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square)
+                .with_pins(pins.clone())
+                .place_tiles(&config);
 
-            // Tile has already collapsed
-            if tiles[choosen_tile].data.is_some() {
-                continue; // Don't count duplicates as an iteration
+            assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+            for y in 0..10 {
+                assert_eq!(*grid.get(Vector3::new(4, y, 0)), Some(5));
             }
+            assert_eq!(*grid.get(Vector3::new(2, 7, 0)), Some(3));
+        }
+    }
 
-            self.collapse_tile(tiles, uncollapsed_tiles, choosen_tile, random, map_size);
+    #[test]
+    fn fixed_boundary_faces_out_with_border_edge() {
+        let tile_datas = pond_tiles();
+        for map_size in [10, 11] {
+            let config = WfcConfig::builder()
+                .map_size(map_size)
+                .boundary(Boundary::Fixed)
+                .border_edge("grass")
+                .max_backtracks(100)
+                .seed(3)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
 
-            // Add neighbours to queue
-            for direction in Direction::iterator() {
-                let neighbour_position = Vector2::<i32>::new(
-                    tiles[choosen_tile].tile_position.x as i32,
-                    tiles[choosen_tile].tile_position.y as i32,
-                ) + direction.get_vector();
-                if !self.within_grid(neighbour_position, map_size) {
-                    continue;
+            assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+            for (position, tile) in grid.iter() {
+                let tile_data = &tile_datas[tile.expect("Every position has a tile")];
+                for direction in Topology::Square.directions() {
+                    if Topology::Square
+                        .neighbour(position, *direction, grid.size())
+                        .is_none()
+                    {
+                        assert_eq!(tile_data.get_edge(*direction), "grass");
+                    }
                 }
+            }
+        }
+    }
 
-                let neighbour_index = self.index2dto1d(
-                    Vector2::<usize>::new(
-                        neighbour_position.x as usize,
-                        neighbour_position.y as usize,
-                    ),
-                    map_size,
-                );
-                let neighbour_tile: &Tile<'_> = &tiles[neighbour_index];
+    #[test]
+    fn fixed_boundary_uses_sockets() {
+        // Coast is asymmetric, so the border only connects to tiles that face out with coast!
+        let mut tile_datas = vec![tile(4, ["land", "land", "land", "land"])];
+        for side in 0..4 {
+            let mut coast = ["land"; 4];
+            coast[side] = "coast!";
+            tile_datas.push(tile(1, coast));
+            coast[(side + 1) % 4] = "coast!";
+            tile_datas.push(tile(1, coast));
+        }
+        // Would face out with the border edge if edges were compared as text
+        tile_datas.push(tile(100, ["coast", "land", "land", "land"]));
 
-                // Check that tile hasn't collapsed
-                if neighbour_tile.data.is_none() {
-                    // Note: tile could already be in queue
-                    tiles_queue.push_back(neighbour_index);
+        let config = WfcConfig::builder()
+            .map_size(6)
+            .boundary(Boundary::Fixed)
+            .border_edge("coast")
+            .seed(3)
+            .build()
+            .unwrap();
+        let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        for (position, tile) in grid.iter() {
+            let tile_data = &tile_datas[tile.expect("Every position has a tile")];
+            for direction in Topology::Square.directions() {
+                if Topology::Square
+                    .neighbour(position, *direction, grid.size())
+                    .is_none()
+                {
+                    assert_eq!(tile_data.get_edge(*direction), "coast!");
                 }
             }
+        }
+    }
 
-            *iterations += 1;
+    #[test]
+    fn wrapping_boundary_has_no_seams() {
+        for (topology, tile_datas) in [
+            (Topology::Square, pond_tiles()),
+            (Topology::Hex, hex_river_tiles()),
+        ] {
+            let config = WfcConfig::builder()
+                .topology(topology)
+                .map_size(8)
+                .boundary(Boundary::Wrapping)
+                .max_backtracks(100)
+                .seed(5)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, topology).place_tiles(&config);
+
+            assert_valid(&tile_datas, &grid, topology, Boundary::Wrapping);
         }
     }
 
-    // Collapese tiles in an order of left to right, down to up
-    fn ordered_placement_strategy(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
-        iterations: &mut u32,
-        map_size: usize,
-        max_iterations: u32,
-        random: &mut StdRng,
-    ) {
-        for tile_index in 0..(map_size * map_size) {
-            if *iterations > max_iterations {
-                return;
-            }
+    #[test]
+    fn weighted_entropy() {
+        let tile_datas = pond_tiles();
+        let wfc = WFC::new(&tile_datas, Topology::Square);
+        let entropy = |options: Vec<TileId>| {
+            wfc.entropy(&Tile::new(options, Vector3::zeros()), Vector3::new(1, 1, 1))
+        };
 
-            uncollapsed_tiles.remove(&tile_index);
-            self.collapse_tile(tiles, uncollapsed_tiles, tile_index, random, map_size);
-            *iterations += 1;
+        // Grass is 16 times as likely as a pond, so three options can have less entropy than two
+        assert!(entropy(vec![0, 1, 2]) < entropy(vec![1, 2]));
+        assert!((entropy(vec![1, 2]) - 2.0_f32.ln()).abs() < 1e-6);
+        assert_eq!(entropy(vec![3]), 0.0);
+    }
+
+    // Collapses the tile positions from the top right down, stopping after max_tiles tiles
+    struct TopDownSelector {
+        max_tiles: usize,
+    }
+
+    impl CellSelector for TopDownSelector {
+        fn next_tile(&mut self, wave: &mut Wave, _random: &mut StdRng) -> Option<usize> {
+            self.max_tiles = self.max_tiles.checked_sub(1)?;
+            wave.uncollapsed_tiles().last().copied()
         }
     }
 
-    // Collapses the tiles in the order of least entropy first
-    fn least_entropy_placement_strategy(
-        &mut self,
-        tiles: &mut [Tile],
-        uncollapsed_tiles: &mut BTreeSet<usize>,
-        iterations: &mut u32,
-        map_size: usize,
-        max_iterations: u32,
-        random: &mut StdRng,
-    ) {
-        self.collapse_center_tile(tiles, uncollapsed_tiles, random, map_size);
-
-        while *iterations < max_iterations && !uncollapsed_tiles.is_empty() {
-            // Find element with least entropy
-            let mut least_entropy = usize::MAX;
-            let mut least_entropy_index = 0;
-
-            for tile_index in uncollapsed_tiles.iter() {
-                let tile_entropy = tiles[*tile_index].possible_tiles.len();
-                if tile_entropy < least_entropy {
-                    least_entropy = tile_entropy;
-                    least_entropy_index = *tile_index;
+    #[test]
+    fn custom_cell_selector() {
+        let tile_datas = pond_tiles();
+        let config = WfcConfig::builder()
+            .map_size(10)
+            .max_backtracks(100)
+            .seed(3)
+            .build()
+            .unwrap();
+        let wfc = WFC::new(&tile_datas, Topology::Square);
+
+        let grid = wfc.place_tiles_with(&config, &mut TopDownSelector { max_tiles: 100 });
+        assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+
+        // Only the top row is collapsed when the selector stops after it
+        let grid = wfc.place_tiles_with(&config, &mut TopDownSelector { max_tiles: 10 });
+        for (position, tile) in grid.iter() {
+            assert_eq!(tile.is_some(), position.y == 9);
+        }
+    }
+
+    #[test]
+    fn backtracking_resolves_contradictions() {
+        let (tile_datas, rules) = three_colors();
+        let wfc = WFC::with_rules(&tile_datas, Topology::Square, rules);
+        let config = WfcConfig::builder()
+            .map_size(8)
+            .max_backtracks(0)
+            .seed(13)
+            .build()
+            .unwrap();
+        assert!(wfc.place_tiles(&config).cells().contains(&None));
+
+        let config = WfcConfig {
+            max_backtracks: 100,
+            ..config
+        };
+        let grid = wfc.place_tiles(&config);
+        assert!(grid.cells().iter().all(Option::is_some));
+        for (position, tile) in grid.iter() {
+            for direction in Topology::Square.iterator() {
+                if let Some(neighbour) =
+                    Topology::Square.neighbour(position, direction, grid.size())
+                {
+                    assert_ne!(grid.get(neighbour), tile);
                 }
             }
+        }
+    }
+
+    #[test]
+    fn undone_collapses_dont_use_iterations() {
+        let (tile_datas, rules) = three_colors();
+        let wfc = WFC::with_rules(&tile_datas, Topology::Square, rules);
+        let config = WfcConfig::builder()
+            .map_size(10)
+            .max_iterations(100)
+            .max_backtracks(100)
+            .seed(71)
+            .build()
+            .unwrap();
 
-            uncollapsed_tiles.remove(&least_entropy_index);
-            self.collapse_tile(
+        let mut stepper = wfc.stepper(&config, config.placement_strategy.cell_selector());
+        // Every tile position is collapsed once, and some of them again after being undone
+        assert!(stepper.by_ref().count() > 100);
+        assert!(stepper.grid().cells().iter().all(Option::is_some));
+        assert!(stepper.wave().entropy_queue.len() <= ENTROPY_ENTRIES_PER_TILE * 100);
+    }
+
+    #[test]
+    fn undoing_a_collapse_restores_the_wave() {
+        // The grass limit is reached while the map is generated, which removes grass everywhere
+        let wfc = WFC::new(pond_tiles(), Topology::Square).with_count_limits(vec![CountLimit {
+            tiles: vec![0],
+            min: None,
+            max: Some(Amount::Tiles(3)),
+        }]);
+        let config = WfcConfig::builder()
+            .map_size(6)
+            .max_backtracks(10)
+            .seed(3)
+            .build()
+            .unwrap();
+        let state = |wave: &Wave| {
+            let tiles = wave
+                .tiles
+                .iter()
+                .map(|tile| (tile.data, tile.possible_tiles.clone()))
+                .collect::<Vec<(Option<TileId>, Vec<TileId>)>>();
+            (
                 tiles,
-                uncollapsed_tiles,
-                least_entropy_index,
-                random,
-                map_size,
-            );
-            *iterations += 1;
+                wave.uncollapsed_tiles.clone(),
+                wave.placed_counts.clone(),
+            )
+        };
+
+        for collapsed in 0..6 {
+            let mut stepper = wfc.stepper(&config, config.placement_strategy.cell_selector());
+            stepper.by_ref().take(collapsed).count();
+            let before = state(stepper.wave());
+
+            stepper.next();
+            assert_eq!(stepper.contradictions(), 0);
+            let generation = &mut stepper.generation;
+            let decision = generation.backtracker.history.pop_back().unwrap();
+            let map_size = generation.wave.map_size;
+            wfc.undo(&mut generation.wave, decision.changes, map_size);
+            generation
+                .wave
+                .uncollapsed_tiles
+                .insert(decision.tile_index);
+            assert_eq!(state(stepper.wave()), before);
+        }
+    }
+
+    #[test]
+    fn propagation_reaches_distant_tiles() {
+        // A chain where every tile only fits one tile on its right
+        let tile_datas = vec![tile(1, ["", "", "", ""]); 3];
+        let mut rules = AdjacencyRules::new(3);
+        for (tile, right_tile) in [(0, 1), (1, 2), (2, 0)] {
+            rules.allow(tile, Direction::Right, right_tile);
+        }
+        let config = WfcConfig::builder()
+            .map_width(5)
+            .map_height(1)
+            .build()
+            .unwrap();
+        let wfc = WFC::with_rules(&tile_datas, Topology::Square, rules);
+
+        // Collapsing one tile position decides the tiles of the whole row
+        let mut stepper = wfc.stepper(&config, config.placement_strategy.cell_selector());
+        assert!(stepper
+            .wave()
+            .tiles()
+            .iter()
+            .all(|tile| tile.possible_tiles.len() == 3));
+        let collapsed = stepper.next().unwrap();
+        let collapsed_tile = stepper.grid().cells()[collapsed].unwrap();
+        for (index, tile) in stepper.wave().tiles().iter().enumerate() {
+            if index != collapsed {
+                let distance = index as i32 - collapsed as i32;
+                let expected = (collapsed_tile as i32 + distance).rem_euclid(3) as TileId;
+                assert_eq!(tile.possible_tiles, [expected]);
+            }
+        }
+    }
+
+    #[test]
+    fn stepper_gives_same_map() {
+        let tile_datas = pond_tiles();
+        for placement_strategy in [PlacementStrategy::LeastEntropy, PlacementStrategy::Growing] {
+            let config = WfcConfig::builder()
+                .placement_strategy(placement_strategy)
+                .map_size(10)
+                .max_backtracks(100)
+                .seed(8)
+                .build()
+                .unwrap();
+            let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+            let mut stepper = WFC::new(&tile_datas, Topology::Square)
+                .into_stepper(&config, placement_strategy.cell_selector());
+            assert!(stepper.grid().cells().iter().all(Option::is_none));
+            let first_tile = stepper.next().unwrap();
+            assert!(stepper.grid().cells()[first_tile].is_some());
+            stepper.by_ref().count();
+            assert_eq!(stepper.grid(), grid);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_map() {
+        let tile_datas = pond_tiles();
+        let config = WfcConfig::builder()
+            .map_size(16)
+            .max_backtracks(100)
+            .seed(42)
+            .build()
+            .unwrap();
+        let generate = || WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        assert_eq!(generate(), generate());
+    }
+
+    #[test]
+    fn earlier_runs_dont_change_the_next_map() {
+        let tile_datas = pond_tiles();
+        let config = WfcConfig::builder()
+            .map_size(8)
+            .max_backtracks(100)
+            .seed(4)
+            .build()
+            .unwrap();
+        let wrapping_config = WfcConfig {
+            boundary: Boundary::Wrapping,
+            placement_strategy: PlacementStrategy::Random,
+            ..config.clone()
+        };
+        let wfc = WFC::new(&tile_datas, Topology::Square);
+
+        let grid = wfc.place_tiles(&config);
+        wfc.place_tiles(&wrapping_config);
+        assert_eq!(wfc.place_tiles(&config), grid);
+        let mut stepper = wfc.stepper(&config, config.placement_strategy.cell_selector());
+        stepper.by_ref().count();
+        assert_eq!(stepper.grid(), grid);
+    }
+
+    #[test]
+    fn unmatched_edges_only_face_the_border() {
+        // Nothing has a lava down edge, so this tile only fits in the top row
+        let mut tile_datas = pond_tiles();
+        tile_datas.push(tile(1000, ["lava", "grass", "grass", "grass"]));
+
+        let config = WfcConfig::builder()
+            .placement_strategy(PlacementStrategy::Random)
+            .map_size(8)
+            .seed(1)
+            .build()
+            .unwrap();
+        let grid = WFC::new(&tile_datas, Topology::Square).place_tiles(&config);
+
+        assert_valid(&tile_datas, &grid, Topology::Square, Boundary::Open);
+        for (position, tile) in grid.iter() {
+            if *tile == Some(7) {
+                assert_eq!(position.y, 7);
+            }
         }
     }
 }
@@ -0,0 +1,213 @@
+use anyhow::ensure;
+use nalgebra::Vector3;
+
+use crate::grid::Grid;
+
+// Multiplier for the weight of a tile that depends on where in the map the tile is placed.
+// Every weight map gives a value between 0 and 1
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum WeightMap {
+    #[default]
+    Uniform, // Same weight everywhere
+    Center, // Highest in the center of the map, 0 in the corners
+    Edge,   // Highest in the corners of the map, 0 in the center
+    // Smooth random blobs about scale tiles wide
+    Noise {
+        scale: f32,
+        seed: u64,
+    },
+    Image(Grid<f32>), // Stretched over the map, one layer is used for all layers
+}
+
+impl WeightMap {
+    // Returns: the multiplier for a tile placed at position in a map of map_size
+    pub fn factor(&self, position: Vector3<usize>, map_size: Vector3<usize>) -> f32 {
+        match self {
+            WeightMap::Uniform => 1.0,
+            WeightMap::Center => 1.0 - distance_from_center(position, map_size),
+            WeightMap::Edge => distance_from_center(position, map_size),
+            WeightMap::Noise { scale, seed } => {
+                value_noise(position.x as f32 / scale, position.y as f32 / scale, *seed)
+            }
+            WeightMap::Image(image) => {
+                let x = position.x * image.width() / map_size.x;
+                let y = position.y * image.height() / map_size.y;
+                *image.get(Vector3::new(x, y, 0))
+            }
+        }
+    }
+}
+
+// Returns: 0 in the center and 1 in the corners
+fn distance_from_center(position: Vector3<usize>, map_size: Vector3<usize>) -> f32 {
+    let center_x = (map_size.x - 1) as f32 / 2.0;
+    let center_y = (map_size.y - 1) as f32 / 2.0;
+    let max_distance = (center_x * center_x + center_y * center_y).sqrt();
+    if max_distance == 0.0 {
+        return 0.0;
+    }
+    let (dx, dy) = (position.x as f32 - center_x, position.y as f32 - center_y);
+    (dx * dx + dy * dy).sqrt() / max_distance
+}
+
+// Random values on whole coordinates that are smoothly blended in between
+fn value_noise(x: f32, y: f32, seed: u64) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let corner = |dx: f32, dy: f32| lattice_value((x0 + dx) as i64, (y0 + dy) as i64, seed);
+
+    let bottom = corner(0.0, 0.0) * (1.0 - tx) + corner(1.0, 0.0) * tx;
+    let top = corner(0.0, 1.0) * (1.0 - tx) + corner(1.0, 1.0) * tx;
+    bottom * (1.0 - ty) + top * ty
+}
+
+// Returns: a value between 0 and 1 that only depends on the coordinates and seed (splitmix64)
+fn lattice_value(x: i64, y: i64, seed: u64) -> f32 {
+    let mut hash =
+        seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (y as u64).rotate_left(32);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// Reads a grayscale PGM image (P2 or P5), white gives the full weight and black no weight
+pub fn load_image(path: &str) -> anyhow::Result<WeightMap> {
+    let bytes = std::fs::read(path)
+        .map_err(|_| anyhow::format_err!("Could not find weight image {}", path))?;
+
+    parse_image(&bytes, path)
+}
+
+// Source is the name of where the image comes from, used in error messages
+pub fn parse_image(bytes: &[u8], source: &str) -> anyhow::Result<WeightMap> {
+    // The header is the format, width, height and max value separated by whitespace
+    let mut header = vec![];
+    let mut position = 0;
+    while header.len() < 4 && position < bytes.len() {
+        match bytes[position] {
+            b'#' => {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+            }
+            byte if byte.is_ascii_whitespace() => position += 1,
+            _ => {
+                let start = position;
+                while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                    position += 1;
+                }
+                header.push(String::from_utf8_lossy(&bytes[start..position]).into_owned());
+            }
+        }
+    }
+    ensure!(
+        header.len() == 4 && (header[0] == "P2" || header[0] == "P5"),
+        "{} is not a grayscale PGM image",
+        source
+    );
+    let numbers = header[1..]
+        .iter()
+        .map(|value| value.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| anyhow::format_err!("{} has an invalid PGM header", source))?;
+    let (width, height, max_value) = (numbers[0], numbers[1], numbers[2]);
+    ensure!(
+        width > 0 && height > 0 && (1..256).contains(&max_value),
+        "{} has to be at least 1x1 pixels with at most 8 bits per pixel",
+        source
+    );
+
+    let pixels = if header[0] == "P5" {
+        // A single whitespace character separates the header from the pixels
+        bytes
+            .get(position + 1..)
+            .unwrap_or_default()
+            .iter()
+            .map(|pixel| *pixel as usize)
+            .collect::<Vec<usize>>()
+    } else {
+        String::from_utf8_lossy(&bytes[position..])
+            .split_ascii_whitespace()
+            .map(|value| value.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| anyhow::format_err!("{} has an invalid pixel value", source))?
+    };
+    ensure!(
+        pixels.len() >= width * height,
+        "{} does not contain {}x{} pixels",
+        source,
+        width,
+        height
+    );
+
+    // The image starts with the top row and the grid with the bottom row
+    let cells = (0..height)
+        .rev()
+        .flat_map(|y| pixels[y * width..(y + 1) * width].iter())
+        .map(|pixel| (*pixel).min(max_value) as f32 / max_value as f32)
+        .collect();
+    Ok(WeightMap::Image(Grid::new(
+        Vector3::new(width, height, 1),
+        cells,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::{parse_image, WeightMap};
+
+    #[test]
+    fn center_and_edge_weights() {
+        let map_size = Vector3::new(9, 9, 1);
+        let center = Vector3::new(4, 4, 0);
+        let corner = Vector3::new(0, 8, 0);
+
+        assert_eq!(WeightMap::Center.factor(center, map_size), 1.0);
+        assert!(WeightMap::Center.factor(corner, map_size) < 1e-6);
+        assert_eq!(WeightMap::Edge.factor(center, map_size), 0.0);
+        assert_eq!(WeightMap::Uniform.factor(corner, map_size), 1.0);
+    }
+
+    #[test]
+    fn noise_is_smooth_and_seeded() {
+        let map_size = Vector3::new(20, 20, 1);
+        let noise = WeightMap::Noise {
+            scale: 5.0,
+            seed: 3,
+        };
+        for x in 0..19 {
+            let position = Vector3::new(x, 7, 0);
+            let factor = noise.factor(position, map_size);
+            assert!((0.0..=1.0).contains(&factor));
+            assert!((factor - noise.factor(position + Vector3::x(), map_size)).abs() < 0.5);
+        }
+        let other_seed = WeightMap::Noise {
+            scale: 5.0,
+            seed: 4,
+        };
+        assert!(
+            (0..20).any(|y| noise.factor(Vector3::new(2, y, 0), map_size)
+                != other_seed.factor(Vector3::new(2, y, 0), map_size))
+        );
+    }
+
+    #[test]
+    fn image_is_stretched_over_map() {
+        // White top row and black bottom row
+        let image = parse_image(b"P2\n# comment\n2 2\n255\n255 255\n0 0\n", "test").unwrap();
+        let map_size = Vector3::new(10, 10, 1);
+
+        assert_eq!(image.factor(Vector3::new(7, 9, 0), map_size), 1.0);
+        assert_eq!(image.factor(Vector3::new(7, 2, 0), map_size), 0.0);
+        assert_eq!(
+            parse_image(b"P5 1 2 255\n\xff\x00", "test").unwrap(),
+            parse_image(b"P2 1 2 255 255 0", "test").unwrap()
+        );
+        assert!(parse_image(b"P2 2 2 255 1 2 3", "test").is_err());
+        assert!(parse_image(b"P6 1 1 255 0 0 0", "test").is_err());
+    }
+}